@@ -1,65 +1,261 @@
-use alvinw_chess::pos::BoardPos;
+use std::rc::Rc;
 
-pub struct MoveAnimation {
-    pub running: bool,
-    animation_start_time: u64,
+use alvinw_chess::{game::Game, pos::BoardPos, piece::PieceType, board};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::Clock;
+
+/// Animation durations in milliseconds that can be chosen with M, 0 turns
+/// animations off.
+pub const ANIMATION_DURATIONS: [u64; 4] = [0, 80, 150, 300];
+
+/// Maps the elapsed fraction of an animation to the fraction of the distance covered.
+///
+/// Written in the settings file in kebab case, e.g. `ease-out-cubic`.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    Linear,
+    /// Starts fast and slows down, the original animation curve.
+    EaseOutSqrt,
+    EaseOutCubic,
+    EaseInOutCubic,
+}
+
+impl Easing {
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOutSqrt => t.sqrt(),
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                }
+                else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            },
+        }
+    }
+}
+
+/// A piece sliding from one square to another.
+struct PieceAnimation {
+    from: BoardPos,
+    to: BoardPos,
+    start_time: u64,
+    duration: u64,
+}
+
+/// A piece that has left the board fading out where it stood.
+struct FadeAnimation {
+    square: BoardPos,
+    piece: PieceType,
+    color: board::Color,
+    start_time: u64,
     duration: u64,
-    move_from: Option<BoardPos>,
-    move_dest: Option<BoardPos>
+}
+
+/// The pieces currently sliding across or fading from the board.
+pub struct MoveAnimation {
+    /// Duration of new animations in milliseconds, 0 to move pieces instantly.
+    pub duration: u64,
+    pub easing: Easing,
+    animations: Vec<PieceAnimation>,
+    fades: Vec<FadeAnimation>,
+    time: Rc<dyn Clock>,
 }
 
 impl MoveAnimation {
-    pub fn new() -> MoveAnimation {
+    pub fn new(time: Rc<dyn Clock>) -> MoveAnimation {
         MoveAnimation {
-            running: false,
-            animation_start_time: 0,
-            duration: 0,
-            move_from: None,
-            move_dest: None
+            duration: 150,
+            easing: Easing::EaseOutSqrt,
+            animations: Vec::new(),
+            fades: Vec::new(),
+            time,
         }
     }
 
-    pub fn target(&self) -> Option<BoardPos> {
-        self.move_dest.clone()
+    pub fn is_running(&self) -> bool {
+        !self.animations.is_empty() || !self.fades.is_empty()
     }
 
-    pub fn set_animation(&mut self, start_time: u64, duration: u64, from: &BoardPos, to: &BoardPos) {
-        if !self.running {
-            self.running = true;
-            self.animation_start_time = start_time;
-            self.duration = duration;
-            self.move_from = Some(from.clone());
-            self.move_dest = Some(to.clone());
+    /// Animates the change from `before` to `after`: every piece that moved
+    /// slides to its new square and every piece that left the board fades out.
+    ///
+    /// Pieces are matched by type and colour, so castling moves the rook as
+    /// well and an en passant victim fades from its own square. A promoted
+    /// piece is matched with the pawn it replaced.
+    pub fn animate_move(&mut self, before: &Game, after: &Game) {
+        let start_time = self.time.now();
+        let mut vacated = Vec::new();
+        let mut arrived = Vec::new();
+        for rank in 0..8 {
+            for file in 0..8 {
+                let pos = BoardPos::new(file, rank);
+                let old = before.get_tile(&pos).map(|tile| (tile.piece(), tile.color()));
+                let new = after.get_tile(&pos).map(|tile| (tile.piece(), tile.color()));
+                if old != new {
+                    vacated.extend(old.map(|piece| (pos.clone(), piece)));
+                    arrived.extend(new.map(|piece| (pos, piece)));
+                }
+            }
+        }
+
+        for (to, (piece, color)) in arrived {
+            let same_piece = vacated.iter().position(|(from, other)| *from != to && *other == (piece, color));
+            let promoted_pawn = || vacated.iter().position(|(from, other)| *from != to && *other == (PieceType::Pawn, color));
+            if let Some(index) = same_piece.or_else(promoted_pawn) {
+                let (from, _) = vacated.remove(index);
+                self.set_animation(&from, &to);
+            }
+        }
+
+        if self.duration == 0 {
+            return;
+        }
+        for (square, (piece, color)) in vacated {
+            self.fades.retain(|fade| fade.square != square);
+            self.fades.push(FadeAnimation { square, piece, color, start_time, duration: self.duration });
         }
     }
 
-    pub fn cancel_current_animation(&mut self) {
-        self.running = false;
+    /// Slides the piece now standing on `to` from `from`, replacing any
+    /// animation already running towards `to`.
+    pub fn set_animation(&mut self, from: &BoardPos, to: &BoardPos) {
+        self.animations.retain(|animation| animation.to != *to);
+        if self.duration == 0 {
+            return;
+        }
+        self.animations.push(PieceAnimation {
+            from: from.clone(),
+            to: to.clone(),
+            start_time: self.time.now(),
+            duration: self.duration,
+        });
     }
 
-    pub fn update_animation(&mut self, current_time: u64) -> Option<(f64, f64)> {
+    /// Stops sliding the piece on `target`, leaving other animations running.
+    pub fn cancel_animation(&mut self, target: &BoardPos) {
+        self.animations.retain(|animation| animation.to != *target);
+    }
 
-        if let Some(dest) = self.move_dest.clone() {
-            if !self.running {
-                self.running = current_time >= self.animation_start_time && 
-                    current_time < self.animation_start_time + self.duration;
-            }
-    
-            if self.running {
-                let animation_progress: f64 = ((current_time as f64 - self.animation_start_time as f64) / self.duration as f64).sqrt();
+    pub fn cancel_animations(&mut self) {
+        self.animations.clear();
+        self.fades.clear();
+    }
 
-                if animation_progress >= 1.0 {
-                    self.running = false;
-                    return Some((dest.file() as f64, dest.rank() as f64));
-                }
-                else {
-                    let from = self.move_from.clone().expect("Always exists it there is a destination");
-                    let rank = from.rank() as f64 + (dest.rank() as f64 - from.rank() as f64) * animation_progress;
-                    let file = from.file() as f64 + (dest.file() as f64 - from.file() as f64) * animation_progress;
-                    return Some((file, rank));
-                }
-            }
-        }
-        return None;
+    /// Drops the animations that have finished.
+    pub fn update(&mut self) {
+        let current_time = self.time.now();
+        self.animations.retain(|animation| current_time < animation.start_time + animation.duration);
+        self.fades.retain(|fade| current_time < fade.start_time + fade.duration);
+    }
+
+    /// Returns the pieces fading out with their square and opacity.
+    pub fn fading_pieces(&self) -> Vec<(BoardPos, PieceType, board::Color, f32)> {
+        let current_time = self.time.now();
+        self.fades.iter().map(|fade| {
+            let elapsed = current_time.saturating_sub(fade.start_time) as f64;
+            let opacity = 1.0 - self.easing.apply(elapsed / fade.duration as f64);
+            (fade.square.clone(), fade.piece, fade.color, opacity as f32)
+        }).collect()
+    }
+
+    /// Returns the board position `(file, rank)` to draw the piece standing
+    /// on `target` at, `None` if it is not animated.
+    pub fn position(&self, target: &BoardPos) -> Option<(f64, f64)> {
+        let animation = self.animations.iter().find(|animation| animation.to == *target)?;
+        let current_time = self.time.now();
+
+        let elapsed = current_time.saturating_sub(animation.start_time) as f64;
+        let progress = self.easing.apply(elapsed / animation.duration as f64);
+
+        let from = &animation.from;
+        let to = &animation.to;
+        let rank = from.rank() as f64 + (to.rank() as f64 - from.rank() as f64) * progress;
+        let file = from.file() as f64 + (to.file() as f64 - from.file() as f64) * progress;
+        Some((file, rank))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_san;
+    use crate::rules::play;
+    use crate::utils::FakeClock;
+
+    fn animation(time: &Rc<FakeClock>) -> MoveAnimation {
+        let mut animation = MoveAnimation::new(time.clone());
+        animation.duration = 100;
+        animation.easing = Easing::Linear;
+        animation
+    }
+
+    fn after(game: &Game, san: &str) -> Game {
+        play(game, &parse_san(game, san).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn piece_slides_over_the_duration() {
+        let time = Rc::new(FakeClock::new(500));
+        let mut animation = animation(&time);
+        let before = Game::new();
+        let after = after(&before, "Nf3");
+        animation.animate_move(&before, &after);
+
+        let target = BoardPos::new(5, 2);
+        assert_eq!(animation.position(&target), Some((6.0, 0.0)));
+        time.advance(50);
+        assert_eq!(animation.position(&target), Some((5.5, 1.0)));
+        time.advance(50);
+        animation.update();
+        assert!(!animation.is_running());
+        assert_eq!(animation.position(&target), None);
+    }
+
+    #[test]
+    fn easing_shapes_the_progress() {
+        let time = Rc::new(FakeClock::new(0));
+        let mut animation = animation(&time);
+        animation.easing = Easing::EaseOutCubic;
+        animation.set_animation(&BoardPos::new(0, 0), &BoardPos::new(0, 4));
+        time.advance(50);
+        assert_eq!(animation.position(&BoardPos::new(0, 4)), Some((0.0, 3.5)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn captured_piece_fades_out() {
+        let time = Rc::new(FakeClock::new(0));
+        let mut animation = animation(&time);
+        let game = after(&after(&Game::new(), "e4"), "d5");
+        let capture = after(&game, "exd5");
+        animation.animate_move(&game, &capture);
+
+        time.advance(25);
+        let fading = animation.fading_pieces();
+        assert_eq!(fading.len(), 1);
+        let (square, piece, color, opacity) = &fading[0];
+        assert!(*square == BoardPos::new(3, 4) && *piece == PieceType::Pawn && *color == board::Color::Black);
+        assert_eq!(*opacity, 0.75);
+        assert!(animation.position(&BoardPos::new(3, 4)).is_some());
+
+        time.advance(75);
+        animation.update();
+        assert!(animation.fading_pieces().is_empty());
+    }
+
+    #[test]
+    fn zero_duration_moves_instantly() {
+        let time = Rc::new(FakeClock::new(0));
+        let mut animation = animation(&time);
+        animation.duration = 0;
+        let before = Game::new();
+        animation.animate_move(&before, &after(&before, "e4"));
+        assert!(!animation.is_running());
+    }
+}
@@ -0,0 +1,237 @@
+use alvinw_chess::board;
+
+/// How time is added to a player's clock after each move.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TimeMode {
+    /// No time is ever added.
+    SuddenDeath,
+    /// A fixed increment is added after every move.
+    Fischer,
+    /// The time used for the move is given back, up to the delay.
+    Bronstein,
+    /// The clock only starts counting down once the delay has passed.
+    SimpleDelay,
+}
+
+/// A time control, all durations in milliseconds.
+#[derive(Clone, Copy, PartialEq)]
+pub struct TimeControl {
+    pub name: &'static str,
+    pub initial: u64,
+    pub mode: TimeMode,
+    /// Increment for Fischer, delay for Bronstein and simple delay.
+    pub bonus: u64,
+}
+
+/// Time controls selectable in the GUI. `None` means the game is untimed.
+pub const TIME_CONTROLS: [Option<TimeControl>; 6] = [
+    None,
+    Some(TimeControl { name: "1+0", initial: 60_000, mode: TimeMode::SuddenDeath, bonus: 0 }),
+    Some(TimeControl { name: "3+2", initial: 180_000, mode: TimeMode::Fischer, bonus: 2_000 }),
+    Some(TimeControl { name: "5+0", initial: 300_000, mode: TimeMode::SuddenDeath, bonus: 0 }),
+    Some(TimeControl { name: "5 d3", initial: 300_000, mode: TimeMode::SimpleDelay, bonus: 3_000 }),
+    Some(TimeControl { name: "10 b5", initial: 600_000, mode: TimeMode::Bronstein, bonus: 5_000 }),
+];
+
+/// Remaining time below which a clock is shown as low.
+const LOW_TIME: u64 = 10_000;
+
+/// Keeps the remaining time of both players.
+pub struct ChessClock {
+    pub control: TimeControl,
+    /// Remaining time at the start of the current turn, indexed by `side_index`.
+    remaining: [u64; 2],
+    /// The side whose clock is running.
+    active: Option<usize>,
+    turn_start: u64,
+}
+
+impl ChessClock {
+    /// Creates a stopped clock with the initial time on both sides.
+    pub fn new(control: TimeControl) -> ChessClock {
+        ChessClock {
+            control,
+            remaining: [control.initial; 2],
+            active: None,
+            turn_start: 0,
+        }
+    }
+
+    /// Returns whether the clock is running.
+    pub fn is_running(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Starts or restarts the clock of `color` without changing the time of either side.
+    pub fn start(&mut self, now: u64, color: board::Color) {
+        self.stop(now);
+        self.active = Some(side_index(color));
+        self.turn_start = now;
+    }
+
+    /// Stops the running clock, charging the elapsed time but not adding any bonus.
+    pub fn stop(&mut self, now: u64) {
+        if let Some(side) = self.active {
+            self.remaining[side] = self.remaining_for(side, now);
+            self.active = None;
+        }
+    }
+
+    /// Ends the turn of the running side, adding its bonus, and starts the other clock.
+    pub fn switch(&mut self, now: u64) {
+        if let Some(side) = self.active {
+            let elapsed = now.saturating_sub(self.turn_start);
+            let mut remaining = self.remaining_for(side, now);
+            if remaining > 0 {
+                remaining += match self.control.mode {
+                    TimeMode::SuddenDeath | TimeMode::SimpleDelay => 0,
+                    TimeMode::Fischer => self.control.bonus,
+                    TimeMode::Bronstein => elapsed.min(self.control.bonus),
+                };
+            }
+            self.remaining[side] = remaining;
+            self.active = Some(1 - side);
+            self.turn_start = now;
+        }
+    }
+
+    /// Returns the time left for both sides, White first.
+    pub fn times(&self, now: u64) -> [u64; 2] {
+        [self.remaining_for(0, now), self.remaining_for(1, now)]
+    }
+
+    /// Sets the time left for both sides, White first. A running clock
+    /// counts down from `now`.
+    pub fn set_times(&mut self, times: [u64; 2], now: u64) {
+        self.remaining = times;
+        self.turn_start = now;
+    }
+
+    /// Returns the time left for `color`.
+    pub fn remaining(&self, color: board::Color, now: u64) -> u64 {
+        self.remaining_for(side_index(color), now)
+    }
+
+    /// Returns whether `color` is running low on time.
+    pub fn is_low(&self, color: board::Color, now: u64) -> bool {
+        self.remaining(color, now) < LOW_TIME
+    }
+
+    /// Returns the side whose flag has fallen, if any.
+    pub fn flagged(&self, now: u64) -> Option<board::Color> {
+        let side = self.active?;
+        if self.remaining_for(side, now) == 0 {
+            return Some(if side == 0 { board::Color::White } else { board::Color::Black });
+        }
+        None
+    }
+
+    fn remaining_for(&self, side: usize, now: u64) -> u64 {
+        if self.active != Some(side) {
+            return self.remaining[side];
+        }
+        let mut elapsed = now.saturating_sub(self.turn_start);
+        if self.control.mode == TimeMode::SimpleDelay {
+            elapsed = elapsed.saturating_sub(self.control.bonus);
+        }
+        self.remaining[side].saturating_sub(elapsed)
+    }
+}
+
+/// Formats a duration as `m:ss`, with tenths of a second when below twenty seconds.
+pub fn format_time(ms: u64) -> String {
+    let seconds = ms / 1000;
+    if ms < 20_000 {
+        format!("{}:{:02}.{}", seconds / 60, seconds % 60, ms % 1000 / 100)
+    }
+    else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+fn side_index(color: board::Color) -> usize {
+    match color {
+        board::Color::White => 0,
+        board::Color::Black => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{Clock, FakeClock};
+
+    fn started_clock(control: usize, time: &FakeClock) -> ChessClock {
+        let mut clock = ChessClock::new(TIME_CONTROLS[control].unwrap());
+        clock.start(time.now(), board::Color::White);
+        clock
+    }
+
+    #[test]
+    fn only_the_running_side_loses_time() {
+        let time = FakeClock::new(1_000);
+        let mut clock = started_clock(3, &time);
+        time.advance(4_000);
+        assert_eq!(clock.remaining(board::Color::White, time.now()), 296_000);
+        assert_eq!(clock.remaining(board::Color::Black, time.now()), 300_000);
+
+        clock.switch(time.now());
+        time.advance(1_500);
+        assert_eq!(clock.times(time.now()), [296_000, 298_500]);
+
+        clock.stop(time.now());
+        time.advance(10_000);
+        assert!(!clock.is_running());
+        assert_eq!(clock.times(time.now()), [296_000, 298_500]);
+    }
+
+    #[test]
+    fn fischer_adds_the_increment() {
+        let time = FakeClock::new(0);
+        let mut clock = started_clock(2, &time);
+        time.advance(5_000);
+        clock.switch(time.now());
+        assert_eq!(clock.remaining(board::Color::White, time.now()), 177_000);
+    }
+
+    #[test]
+    fn bronstein_gives_back_time_used_up_to_the_delay() {
+        let time = FakeClock::new(0);
+        let mut clock = started_clock(5, &time);
+        time.advance(3_000);
+        clock.switch(time.now());
+        assert_eq!(clock.remaining(board::Color::White, time.now()), 600_000);
+
+        time.advance(8_000);
+        clock.switch(time.now());
+        assert_eq!(clock.remaining(board::Color::Black, time.now()), 597_000);
+    }
+
+    #[test]
+    fn simple_delay_counts_down_after_the_delay() {
+        let time = FakeClock::new(0);
+        let clock = started_clock(4, &time);
+        time.advance(2_000);
+        assert_eq!(clock.remaining(board::Color::White, time.now()), 300_000);
+        time.advance(2_000);
+        assert_eq!(clock.remaining(board::Color::White, time.now()), 299_000);
+    }
+
+    #[test]
+    fn flag_falls_at_zero() {
+        let time = FakeClock::new(0);
+        let clock = started_clock(1, &time);
+        time.advance(59_999);
+        assert!(clock.flagged(time.now()).is_none());
+        assert!(!clock.is_low(board::Color::White, time.now() - 50_000));
+        assert!(clock.is_low(board::Color::White, time.now()));
+        time.advance(1);
+        assert!(clock.flagged(time.now()) == Some(board::Color::White));
+    }
+
+    #[test]
+    fn formats_times() {
+        assert_eq!(format_time(300_000), "5:00");
+        assert_eq!(format_time(19_950), "0:19.9");
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::animation::Easing;
+use crate::theme::{builtin_themes, Theme};
+
+/// User preferences kept between sessions.
+///
+/// They are stored as TOML in `settings.toml` in the user's config
+/// directory, with a `[themes.<name>]` table for every user-defined theme.
+/// Missing keys keep their defaults and colours missing from a theme table
+/// are taken from the first built-in theme.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub theme: String,
+    pub piece_set: String,
+    pub show_coordinates: bool,
+    pub auto_flip: bool,
+    /// Index into `STRENGTH_LEVELS`.
+    pub engine_strength: usize,
+    /// Duration of move animations in milliseconds, 0 for none.
+    pub animation_duration: u64,
+    pub easing: Easing,
+    /// Themes defined in the settings file.
+    #[serde(with = "theme_tables", skip_serializing_if = "Vec::is_empty")]
+    pub themes: Vec<Theme>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+impl Config {
+    /// Creates the default preferences.
+    pub fn new() -> Config {
+        Config {
+            theme: String::from("original"),
+            piece_set: String::from("font"),
+            show_coordinates: true,
+            auto_flip: false,
+            engine_strength: 2,
+            animation_duration: 150,
+            easing: Easing::EaseOutSqrt,
+            themes: Vec::new(),
+        }
+    }
+
+    /// Loads the settings file, falling back to the defaults when it does not
+    /// exist or cannot be read.
+    pub fn load() -> Config {
+        let path = match config_path() {
+            Some(path) => path,
+            None => return Config::new(),
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    println!("Could not read {}: {}", path.display(), err);
+                }
+                return Config::new();
+            }
+        };
+        match Config::parse(&text) {
+            Ok(config) => config,
+            Err(err) => {
+                println!("Could not read {}: {}", path.display(), err);
+                Config::new()
+            }
+        }
+    }
+
+    /// Parses the settings file.
+    pub fn parse(text: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Returns the built-in themes followed by the user-defined ones. A user
+    /// theme with the name of a built-in one replaces it.
+    pub fn all_themes(&self) -> Vec<Theme> {
+        let mut themes = builtin_themes();
+        for theme in &self.themes {
+            match themes.iter_mut().find(|other| other.name == theme.name) {
+                Some(other) => *other = theme.clone(),
+                None => themes.push(theme.clone()),
+            }
+        }
+        themes
+    }
+
+    /// Writes the settings file, creating the config directory if needed.
+    pub fn save(&self) -> io::Result<()> {
+        let path = config_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+}
+
+/// Stores themes as tables of `#rrggbbaa` colours keyed by theme name.
+mod theme_tables {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    use crate::theme::{format_color, parse_color, COLOR_KEYS};
+
+    pub fn serialize<S: Serializer>(themes: &[Theme], serializer: S) -> Result<S::Ok, S::Error> {
+        let tables: BTreeMap<&str, BTreeMap<&str, String>> = themes.iter().map(|theme| {
+            let colors = COLOR_KEYS.iter()
+                .filter_map(|key| theme.color(key).map(|color| (*key, format_color(color))))
+                .collect();
+            (theme.name.as_str(), colors)
+        }).collect();
+        tables.serialize(serializer)
+    }
+
+    /// Unknown colour names and malformed colours are skipped.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Theme>, D::Error> {
+        let tables = BTreeMap::<String, BTreeMap<String, String>>::deserialize(deserializer)?;
+        Ok(tables.into_iter().map(|(name, colors)| {
+            let mut theme = builtin_themes().remove(0);
+            theme.name = name;
+            for (key, value) in colors {
+                if let Some(color) = parse_color(&value) {
+                    theme.set_color(&key, color);
+                }
+            }
+            theme
+        }).collect())
+    }
+}
+
+/// Returns the path of the settings file, `None` if the user has no config directory.
+pub fn config_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(env::var_os("APPDATA")?),
+        None if cfg!(target_os = "macos") => PathBuf::from(env::var_os("HOME")?).join("Library").join("Application Support"),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("olindba-chess-gui").join("settings.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_keys_keep_defaults() {
+        let config = Config::parse("auto_flip = true # flip for hot-seat games\n").unwrap();
+        assert!(config.auto_flip);
+        assert_eq!(config.theme, "original");
+        assert!(config.easing == Easing::EaseOutSqrt);
+    }
+
+    #[test]
+    fn reads_theme_tables() {
+        let text = r##"
+            theme = "night"
+            easing = "ease-in-out-cubic"
+
+            [themes.night]
+            light_square = "#ffffff"
+            dark_square = "#00000080"
+            unknown = "#123456"
+        "##;
+        let config = Config::parse(text).unwrap();
+        assert!(config.easing == Easing::EaseInOutCubic);
+        assert_eq!(config.themes.len(), 1);
+
+        let theme = &config.themes[0];
+        assert_eq!(theme.name, "night");
+        assert_eq!(theme.light_square, [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(theme.dark_square[3], 128.0 / 255.0);
+        assert!(theme.background == builtin_themes()[0].background);
+    }
+
+    #[test]
+    fn saved_settings_read_back() {
+        let mut config = Config::new();
+        config.piece_set = String::from("merida \"classic\"");
+        config.engine_strength = 4;
+        config.easing = Easing::Linear;
+        let mut theme = builtin_themes().remove(2);
+        theme.name = String::from("my wood");
+        config.themes.push(theme);
+
+        // Colours are rounded to whole channel values when written.
+        let text = toml::to_string(&config).unwrap();
+        let read_back = Config::parse(&text).unwrap();
+        assert_eq!(read_back.piece_set, config.piece_set);
+        assert_eq!(read_back.engine_strength, 4);
+        assert!(read_back.easing == Easing::Linear);
+        assert_eq!(read_back.themes[0].name, "my wood");
+        assert_eq!(toml::to_string(&read_back).unwrap(), text);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(Config::parse("engine_strength = \"strong\"").is_err());
+        assert!(Config::parse("theme = ").is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use alvinw_chess::{game::Game, game::GameState, pos::BoardPos, piece::PieceType, board};
+
+use crate::rules::{legal_moves, play, Move};
+
+/// Score of a checkmate, reduced by the number of plies until it happens.
+const MATE_SCORE: i32 = 100_000;
+
+/// How hard the computer opponent thinks.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Strength {
+    /// Maximum search depth in plies.
+    pub depth: u32,
+    /// Maximum thinking time in milliseconds, `None` to always finish `depth`.
+    pub time_limit: Option<u64>,
+}
+
+/// Strength presets selectable in the GUI, weakest first.
+pub const STRENGTH_LEVELS: [Strength; 5] = [
+    Strength { depth: 1, time_limit: None },
+    Strength { depth: 2, time_limit: None },
+    Strength { depth: 3, time_limit: Some(2_000) },
+    Strength { depth: 4, time_limit: Some(5_000) },
+    Strength { depth: 6, time_limit: Some(10_000) },
+];
+
+/// Searches for the best move with iterative deepening alpha-beta.
+///
+/// Equal moves are ordered by a pseudo-random generator seeded with `seed`, so
+/// the same position, strength and seed always give the same move as long as
+/// the time limit is not reached.
+pub fn best_move(game: &Game, strength: Strength, seed: u64) -> Option<Move> {
+    search_best_move(game, strength, seed, &AtomicBool::new(false))
+}
+
+/// Like `best_move`, but gives up as soon as `cancelled` is set.
+fn search_best_move(game: &Game, strength: Strength, seed: u64, cancelled: &AtomicBool) -> Option<Move> {
+    let deadline = strength.time_limit.map(|ms| Instant::now() + Duration::from_millis(ms));
+    let mut rng = XorShift::new(seed);
+
+    let mut root_moves = legal_moves(game);
+    rng.shuffle(&mut root_moves);
+
+    let mut best = root_moves.first().cloned();
+    for depth in 1..=strength.depth.max(1) {
+        let mut search = Search { deadline, cancelled, aborted: false };
+        let mut alpha = -MATE_SCORE - 1;
+        let mut depth_best = None;
+
+        for mv in &root_moves {
+            let next = match play(game, mv) {
+                Some(next) => next,
+                None => continue,
+            };
+            let score = -search.alpha_beta(&next, depth - 1, 1, -MATE_SCORE - 1, -alpha);
+            if search.aborted {
+                break;
+            }
+            if score > alpha {
+                alpha = score;
+                depth_best = Some(mv.clone());
+            }
+        }
+
+        if search.aborted {
+            break;
+        }
+        if let Some(mv) = depth_best {
+            // Search the best move first at the next depth for better pruning.
+            if let Some(index) = root_moves.iter().position(|other| *other == mv) {
+                let first = root_moves.remove(index);
+                root_moves.insert(0, first);
+            }
+            best = Some(mv);
+        }
+    }
+    best
+}
+
+struct Search<'a> {
+    deadline: Option<Instant>,
+    cancelled: &'a AtomicBool,
+    aborted: bool,
+}
+
+impl Search<'_> {
+    /// Negamax alpha-beta, scores are from the side to move's point of view.
+    fn alpha_beta(&mut self, game: &Game, depth: u32, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        if self.cancelled.load(Ordering::Relaxed) {
+            self.aborted = true;
+            return 0;
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                self.aborted = true;
+                return 0;
+            }
+        }
+
+        let moves = legal_moves(game);
+        if moves.is_empty() {
+            return match game.get_state() {
+                GameState::Check(_) | GameState::Checkmate(_) => -MATE_SCORE + ply,
+                _ => 0,
+            };
+        }
+        if depth == 0 {
+            return evaluate(game);
+        }
+
+        for mv in ordered(game, moves) {
+            let next = match play(game, &mv) {
+                Some(next) => next,
+                None => continue,
+            };
+            let score = -self.alpha_beta(&next, depth - 1, ply + 1, -beta, -alpha);
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
+
+/// Puts captures and promotions first, most valuable victims first.
+fn ordered(game: &Game, mut moves: Vec<Move>) -> Vec<Move> {
+    moves.sort_by_key(|mv| {
+        let victim = game.get_tile(&mv.to).map_or(0, |tile| piece_value(tile.piece()));
+        let promotion = mv.promotion.map_or(0, piece_value);
+        -(victim + promotion)
+    });
+    moves
+}
+
+/// Material and piece-square evaluation from the side to move's point of view.
+pub fn evaluate(game: &Game) -> i32 {
+    let mut score = 0;
+    for rank in 0..8 {
+        for file in 0..8 {
+            if let Some(tile) = game.get_tile(&BoardPos::new(file, rank)) {
+                let is_white = tile.color() == board::Color::White;
+                let row = if is_white { 7 - rank as usize } else { rank as usize };
+                let value = piece_value(tile.piece()) + piece_square(tile.piece())[row][file as usize];
+                score += if is_white { value } else { -value };
+            }
+        }
+    }
+
+    match game.current_turn() {
+        board::Color::White => score,
+        board::Color::Black => -score,
+    }
+}
+
+/// Material value of a piece in centipawns.
+pub fn piece_value(piece: PieceType) -> i32 {
+    match piece {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 0,
+    }
+}
+
+/// Piece-square tables as seen from White, eighth rank first.
+fn piece_square(piece: PieceType) -> &'static [[i32; 8]; 8] {
+    const PAWN: [[i32; 8]; 8] = [
+        [0, 0, 0, 0, 0, 0, 0, 0],
+        [50, 50, 50, 50, 50, 50, 50, 50],
+        [10, 10, 20, 30, 30, 20, 10, 10],
+        [5, 5, 10, 25, 25, 10, 5, 5],
+        [0, 0, 0, 20, 20, 0, 0, 0],
+        [5, -5, -10, 0, 0, -10, -5, 5],
+        [5, 10, 10, -20, -20, 10, 10, 5],
+        [0, 0, 0, 0, 0, 0, 0, 0],
+    ];
+    const KNIGHT: [[i32; 8]; 8] = [
+        [-50, -40, -30, -30, -30, -30, -40, -50],
+        [-40, -20, 0, 0, 0, 0, -20, -40],
+        [-30, 0, 10, 15, 15, 10, 0, -30],
+        [-30, 5, 15, 20, 20, 15, 5, -30],
+        [-30, 0, 15, 20, 20, 15, 0, -30],
+        [-30, 5, 10, 15, 15, 10, 5, -30],
+        [-40, -20, 0, 5, 5, 0, -20, -40],
+        [-50, -40, -30, -30, -30, -30, -40, -50],
+    ];
+    const BISHOP: [[i32; 8]; 8] = [
+        [-20, -10, -10, -10, -10, -10, -10, -20],
+        [-10, 0, 0, 0, 0, 0, 0, -10],
+        [-10, 0, 5, 10, 10, 5, 0, -10],
+        [-10, 5, 5, 10, 10, 5, 5, -10],
+        [-10, 0, 10, 10, 10, 10, 0, -10],
+        [-10, 10, 10, 10, 10, 10, 10, -10],
+        [-10, 5, 0, 0, 0, 0, 5, -10],
+        [-20, -10, -10, -10, -10, -10, -10, -20],
+    ];
+    const ROOK: [[i32; 8]; 8] = [
+        [0, 0, 0, 0, 0, 0, 0, 0],
+        [5, 10, 10, 10, 10, 10, 10, 5],
+        [-5, 0, 0, 0, 0, 0, 0, -5],
+        [-5, 0, 0, 0, 0, 0, 0, -5],
+        [-5, 0, 0, 0, 0, 0, 0, -5],
+        [-5, 0, 0, 0, 0, 0, 0, -5],
+        [-5, 0, 0, 0, 0, 0, 0, -5],
+        [0, 0, 0, 5, 5, 0, 0, 0],
+    ];
+    const QUEEN: [[i32; 8]; 8] = [
+        [-20, -10, -10, -5, -5, -10, -10, -20],
+        [-10, 0, 0, 0, 0, 0, 0, -10],
+        [-10, 0, 5, 5, 5, 5, 0, -10],
+        [-5, 0, 5, 5, 5, 5, 0, -5],
+        [0, 0, 5, 5, 5, 5, 0, -5],
+        [-10, 5, 5, 5, 5, 5, 0, -10],
+        [-10, 0, 5, 0, 0, 0, 0, -10],
+        [-20, -10, -10, -5, -5, -10, -10, -20],
+    ];
+    const KING: [[i32; 8]; 8] = [
+        [-30, -40, -40, -50, -50, -40, -40, -30],
+        [-30, -40, -40, -50, -50, -40, -40, -30],
+        [-30, -40, -40, -50, -50, -40, -40, -30],
+        [-30, -40, -40, -50, -50, -40, -40, -30],
+        [-20, -30, -30, -40, -40, -30, -30, -20],
+        [-10, -20, -20, -20, -20, -20, -20, -10],
+        [20, 20, 0, 0, 0, 0, 20, 20],
+        [20, 30, 10, 0, 0, 10, 30, 20],
+    ];
+
+    match piece {
+        PieceType::Pawn => &PAWN,
+        PieceType::Knight => &KNIGHT,
+        PieceType::Bishop => &BISHOP,
+        PieceType::Rook => &ROOK,
+        PieceType::Queen => &QUEEN,
+        PieceType::King => &KING,
+    }
+}
+
+/// Small deterministic pseudo-random generator used for move ordering.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        // Zero is a fixed point of xorshift.
+        XorShift(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+/// A search running on a background thread. Dropping it stops the search
+/// and discards the result.
+pub struct EngineSearch {
+    receiver: Receiver<Option<Move>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl EngineSearch {
+    /// Starts searching `game` on a new thread.
+    pub fn start(game: Game, strength: Strength, seed: u64) -> EngineSearch {
+        let (sender, receiver) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            sender.send(search_best_move(&game, strength, seed, &thread_cancelled)).ok();
+        });
+        EngineSearch { receiver, cancelled }
+    }
+
+    /// Returns the result once the search has finished, `Some(None)` if there
+    /// was no legal move.
+    pub fn poll(&self) -> Option<Option<Move>> {
+        match self.receiver.try_recv() {
+            Ok(mv) => Some(mv),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(None),
+        }
+    }
+}
+
+impl Drop for EngineSearch {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_san;
+
+    /// Plays the given SAN moves from the initial position.
+    fn position_after(moves: &[&str]) -> Game {
+        let mut game = Game::new();
+        for san in moves {
+            let mv = parse_san(&game, san).unwrap_or_else(|_| panic!("bad move {}", san));
+            game = play(&game, &mv).unwrap();
+        }
+        game
+    }
+
+    fn depth(depth: u32) -> Strength {
+        Strength { depth, time_limit: None }
+    }
+
+    #[test]
+    fn same_seed_gives_same_move() {
+        let game = position_after(&["e4", "e5"]);
+        let first = best_move(&game, depth(2), 42);
+        assert!(first.is_some());
+        assert!(first == best_move(&game, depth(2), 42));
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let game = position_after(&["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6"]);
+        let mv = best_move(&game, depth(2), 1).unwrap();
+        assert!(mv.from == BoardPos::new(7, 4) && mv.to == BoardPos::new(5, 6));
+    }
+
+    #[test]
+    fn takes_free_piece_at_depth_one() {
+        let game = position_after(&["e4", "Nc6", "Nf3", "Nd4"]);
+        let mv = best_move(&game, depth(1), 7).unwrap();
+        assert!(mv.from == BoardPos::new(5, 2) && mv.to == BoardPos::new(3, 3));
+    }
+
+    #[test]
+    fn cancelled_search_gives_up() {
+        let cancelled = AtomicBool::new(true);
+        let game = Game::new();
+        let strength = Strength { depth: 6, time_limit: None };
+        // Only the first root move is kept when the first depth is abandoned.
+        assert!(search_best_move(&game, strength, 3, &cancelled) == legal_first(&game, 3));
+    }
+
+    fn legal_first(game: &Game, seed: u64) -> Option<Move> {
+        let mut moves = legal_moves(game);
+        XorShift::new(seed).shuffle(&mut moves);
+        moves.first().cloned()
+    }
+}
@@ -0,0 +1,325 @@
+use std::fmt;
+use std::fs;
+use std::io;
+
+use alvinw_chess::{game::Game, pos::BoardPos, piece::PieceType, board};
+
+use crate::history::GameHistory;
+use crate::notation::{parse_square, square_name};
+
+/// FEN of the standard starting position.
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// File the current position is saved to with Ctrl+F and loaded from with Ctrl+L.
+pub const POSITION_FILE: &str = "position.fen";
+
+/// Error produced while reading a FEN string.
+#[derive(Debug)]
+pub enum FenError {
+    Io(io::Error),
+    /// The text is not a well-formed FEN record.
+    Invalid(&'static str),
+    /// The position is well-formed but cannot be set up on the board.
+    Unsupported,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::Io(err) => write!(f, "{}", err),
+            FenError::Invalid(reason) => write!(f, "invalid FEN: {}", reason),
+            FenError::Unsupported => write!(f, "the chess library can only set up the initial position"),
+        }
+    }
+}
+
+impl From<io::Error> for FenError {
+    fn from(err: io::Error) -> FenError {
+        FenError::Io(err)
+    }
+}
+
+/// A parsed Forsyth-Edwards Notation record.
+#[derive(Clone, PartialEq)]
+pub struct Fen {
+    /// Piece letters indexed by `[rank][file]`, uppercase for White.
+    pub board: [[Option<char>; 8]; 8],
+    pub white_to_move: bool,
+    /// Castling availability as written in FEN, e.g. `KQkq` or `-`.
+    pub castling: String,
+    pub en_passant: Option<BoardPos>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+impl Fen {
+    /// Parses a FEN string.
+    pub fn parse(text: &str) -> Result<Fen, FenError> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        if fields.len() != 6 {
+            return Err(FenError::Invalid("expected six fields"));
+        }
+
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::Invalid("expected eight ranks"));
+        }
+        let mut board = [[None; 8]; 8];
+        for (i, row) in ranks.iter().enumerate() {
+            let rank = 7 - i;
+            let mut file = 0;
+            for c in row.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    file += empty as usize;
+                }
+                else if "pnbrqkPNBRQK".contains(c) {
+                    if file < 8 {
+                        board[rank][file] = Some(c);
+                    }
+                    file += 1;
+                }
+                else {
+                    return Err(FenError::Invalid("unknown piece letter"));
+                }
+            }
+            if file != 8 {
+                return Err(FenError::Invalid("rank does not have eight squares"));
+            }
+        }
+
+        let white_to_move = match fields[1] {
+            "w" => true,
+            "b" => false,
+            _ => return Err(FenError::Invalid("side to move must be w or b")),
+        };
+
+        let castling = fields[2];
+        if castling != "-" && !castling.chars().all(|c| "KQkq".contains(c)) {
+            return Err(FenError::Invalid("bad castling field"));
+        }
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            square => {
+                let chars: Vec<char> = square.chars().collect();
+                if chars.len() != 2 {
+                    return Err(FenError::Invalid("bad en passant square"));
+                }
+                Some(parse_square(chars[0], chars[1]).ok_or(FenError::Invalid("bad en passant square"))?)
+            }
+        };
+
+        let halfmove_clock = fields[4].parse().map_err(|_| FenError::Invalid("bad halfmove clock"))?;
+        let fullmove_number = fields[5].parse().map_err(|_| FenError::Invalid("bad fullmove number"))?;
+
+        Ok(Fen {
+            board,
+            white_to_move,
+            castling: castling.to_string(),
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+        })
+    }
+
+    /// Returns the standard starting position.
+    pub fn initial() -> Fen {
+        Fen::parse(START_FEN).expect("START_FEN is well-formed")
+    }
+
+    /// Sets up the position on a new board.
+    ///
+    /// The chess library can only create the initial position and has no way
+    /// to place pieces, so any other placement, side to move, castling rights
+    /// or en passant square is refused. The move counters are kept by the
+    /// history started from this FEN, not by the board.
+    pub fn to_game(&self) -> Result<Game, FenError> {
+        let initial = Fen::initial();
+        if self.board != initial.board || self.white_to_move != initial.white_to_move ||
+            self.castling != initial.castling || self.en_passant != initial.en_passant {
+            return Err(FenError::Unsupported);
+        }
+        Ok(Game::new())
+    }
+
+    /// Describes the current position of a game.
+    pub fn from_game(game: &Game, history: &GameHistory) -> Fen {
+        Fen::describe(game, history, history.current())
+    }
+
+    /// Describes the position after the first `ply` moves of the game.
+    pub fn at_ply(history: &GameHistory, ply: usize) -> Fen {
+        Fen::describe(history.position(ply), history, ply)
+    }
+
+    /// Works out castling rights, the en passant square and the move
+    /// counters from the moves played since the history's start position.
+    fn describe(game: &Game, history: &GameHistory, ply: usize) -> Fen {
+        let start = history.start_fen();
+        let mut board = [[None; 8]; 8];
+        for (rank, row) in board.iter_mut().enumerate() {
+            for (file, square) in row.iter_mut().enumerate() {
+                if let Some(tile) = game.get_tile(&BoardPos::new(file as u8, rank as u8)) {
+                    *square = Some(piece_char(tile.piece(), tile.color()));
+                }
+            }
+        }
+
+        let moves = &history.moves()[..ply];
+        let untouched = |square: &str| !moves.iter().any(|mv| square_name(&mv.from) == square || square_name(&mv.to) == square);
+
+        let mut castling: String = start.castling.chars().filter(|right| match right {
+            'K' => untouched("e1") && untouched("h1"),
+            'Q' => untouched("e1") && untouched("a1"),
+            'k' => untouched("e8") && untouched("h8"),
+            'q' => untouched("e8") && untouched("a8"),
+            _ => false,
+        }).collect();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match moves.last() {
+            None => start.en_passant.clone(),
+            Some(mv) => {
+                let is_pawn = mv.san.starts_with(|c: char| c.is_ascii_lowercase());
+                let distance = mv.to.rank() as i32 - mv.from.rank() as i32;
+                if is_pawn && distance.abs() == 2 {
+                    Some(BoardPos::new(mv.from.file(), (mv.from.rank() + mv.to.rank()) / 2))
+                }
+                else {
+                    None
+                }
+            },
+        };
+
+        // Pawn moves start with a lowercase file letter in SAN, captures contain an `x`.
+        let quiet_moves = moves.iter().rev()
+            .take_while(|mv| !mv.san.starts_with(|c: char| c.is_ascii_lowercase()) && !mv.san.contains('x'))
+            .count() as u32;
+        let halfmove_clock = if quiet_moves as usize == moves.len() {
+            start.halfmove_clock + quiet_moves
+        }
+        else {
+            quiet_moves
+        };
+        let plies = moves.len() as u32 + if start.white_to_move { 0 } else { 1 };
+
+        Fen {
+            board,
+            white_to_move: game.current_turn() == board::Color::White,
+            castling,
+            en_passant,
+            halfmove_clock,
+            fullmove_number: start.fullmove_number + plies / 2,
+        }
+    }
+}
+
+impl fmt::Display for Fen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.board[rank][file] {
+                    Some(c) => {
+                        if empty > 0 {
+                            write!(f, "{}", empty)?;
+                            empty = 0;
+                        }
+                        write!(f, "{}", c)?;
+                    },
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                write!(f, "{}", empty)?;
+            }
+            if rank > 0 {
+                write!(f, "/")?;
+            }
+        }
+
+        let en_passant = match &self.en_passant {
+            Some(pos) => square_name(pos),
+            None => String::from("-"),
+        };
+        write!(f, " {} {} {} {} {}",
+            if self.white_to_move { "w" } else { "b" },
+            self.castling,
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number)
+    }
+}
+
+/// Reads a FEN record from the first line of a file.
+pub fn read_fen(path: &str) -> Result<Fen, FenError> {
+    let text = fs::read_to_string(path)?;
+    Fen::parse(text.lines().next().unwrap_or(""))
+}
+
+/// Returns the FEN letter of a piece, uppercase for White.
+pub fn piece_char(piece: PieceType, color: board::Color) -> char {
+    let c = match piece {
+        PieceType::Pawn => 'p',
+        PieceType::Knight => 'n',
+        PieceType::Bishop => 'b',
+        PieceType::Rook => 'r',
+        PieceType::Queen => 'q',
+        PieceType::King => 'k',
+    };
+    match color {
+        board::Color::White => c.to_ascii_uppercase(),
+        board::Color::Black => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::MoveRecord;
+    use crate::notation::{parse_san, to_san};
+    use crate::rules::play;
+
+    #[test]
+    fn exports_start_position() {
+        let game = Game::new();
+        let history = GameHistory::new(game.clone());
+        assert_eq!(Fen::from_game(&game, &history).to_string(), START_FEN);
+    }
+
+    #[test]
+    fn parse_round_trips() {
+        let fen = "r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 0 23";
+        assert_eq!(Fen::parse(fen).unwrap().to_string(), fen);
+    }
+
+    #[test]
+    fn start_counters_carry_over() {
+        let fen = Fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 10").unwrap();
+        let game = fen.to_game().unwrap();
+        let mut history = GameHistory::from_fen(game.clone(), fen);
+        assert_eq!(Fen::from_game(&game, &history).fullmove_number, 10);
+
+        let mv = parse_san(&game, "Nf3").unwrap();
+        let next = play(&game, &mv).unwrap();
+        let san = to_san(&game, &next, &mv.from, &mv.to, None);
+        history.push(MoveRecord { from: mv.from, to: mv.to, promotion: None, san, captured: None, clock: None }, next.clone());
+        assert_eq!(Fen::from_game(&next, &history).to_string(),
+                   "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 5 10");
+    }
+
+    #[test]
+    fn refuses_positions_the_board_cannot_set_up() {
+        let fen = Fen::parse("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(matches!(fen.to_game(), Err(FenError::Unsupported)));
+    }
+
+    #[test]
+    fn parse_rejects_malformed_records() {
+        assert!(Fen::parse("8/8/8/8/8/8/8 w - - 0 1").is_err());
+        assert!(Fen::parse("8/8/8/8/8/8/8/8 x - - 0 1").is_err());
+        assert!(Fen::parse("9/8/8/8/8/8/8/8 w - - 0 1").is_err());
+    }
+}
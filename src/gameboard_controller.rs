@@ -1,9 +1,100 @@
 use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
 use piston::GenericEvent;
-use alvinw_chess::{game::Game, pos::BoardPos, game::GameState, piece::PieceType, game::MovePieceError, board};
+use alvinw_chess::{game::Game, pos::BoardPos, game::GameState, piece::PieceType, board};
 
-use crate::animation::MoveAnimation;
-use crate::utils::current_time;
+use crate::animation::{MoveAnimation, ANIMATION_DURATIONS};
+use crate::chess_clock::{ChessClock, TIME_CONTROLS};
+use crate::engine::{evaluate, piece_value, EngineSearch, STRENGTH_LEVELS};
+use crate::fen::{read_fen, Fen, FenError, POSITION_FILE};
+use crate::history::{GameHistory, MoveRecord};
+use crate::new_game::{NewGameDialog, Opponent, StartPosition, CANCEL_ROW, DIALOG_ROWS, START_ROW};
+use crate::notation::{move_names, parse_move, parse_uci, to_san, to_uci};
+use crate::outcome::{self, GameResult, Termination};
+use crate::pgn::{last_saved_game, load_pgn, save_pgn, PgnError, SAVED_GAME_PREFIX};
+use crate::rules::{captured_piece, legal_moves, opponent, Move, PROMOTION_PIECES};
+use crate::uci::UciEngine;
+use crate::utils::{current_time, Clock, MonotonicClock};
+
+/// An action offered by the buttons beside the board.
+#[derive(Clone, Copy, PartialEq)]
+pub enum GameAction {
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+}
+
+impl GameAction {
+    /// Returns the button label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameAction::Resign => "Resign",
+            GameAction::OfferDraw => "Offer draw",
+            GameAction::AcceptDraw => "Accept draw",
+            GameAction::DeclineDraw => "Decline draw",
+        }
+    }
+}
+
+/// What a board command did.
+#[derive(Clone, PartialEq, Debug)]
+pub enum CommandOutcome {
+    /// A piece of the side to move is selected.
+    Selected,
+    /// No piece is selected.
+    Deselected,
+    /// A move was played, given in SAN, with the result if it ended the game.
+    Moved {
+        san: String,
+        result: Option<GameResult>,
+    },
+    /// A pawn reached the last rank and waits for `choose_promotion`.
+    PromotionPending,
+}
+
+/// Why a board command was refused.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CommandError {
+    GameOver,
+    /// The computer plays the side to move.
+    EngineTurn,
+    /// Pieces are still moving from the previous move.
+    Animating,
+    /// A promotion piece must be chosen first.
+    PromotionPending,
+    NoPromotionPending,
+    /// Pawns can only promote to a knight, bishop, rook or queen.
+    InvalidPromotion,
+    IllegalMove,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::GameOver => write!(f, "the game is over"),
+            CommandError::EngineTurn => write!(f, "the computer is to move"),
+            CommandError::Animating => write!(f, "the previous move is still animating"),
+            CommandError::PromotionPending => write!(f, "choose a promotion piece first"),
+            CommandError::NoPromotionPending => write!(f, "there is no promotion to choose"),
+            CommandError::InvalidPromotion => write!(f, "pawns cannot promote to that piece"),
+            CommandError::IllegalMove => write!(f, "illegal move"),
+        }
+    }
+}
+
+/// Returns the rectangle of the `index`th action button, stacked downwards from `position`.
+pub fn button_rect(position: [f64; 2], size: [f64; 2], index: usize) -> [f64; 4] {
+    let y = position[1] + index as f64 * size[1] * 1.25;
+    [position[0], y, size[0], size[1]]
+}
+
+/// Returns the rectangle of a new game dialog row, laid out over the board.
+pub fn dialog_row_rect(pos: [f64; 2], size: f64, row: usize) -> [f64; 4] {
+    button_rect([pos[0] + size * 0.15, pos[1] + size * 0.2], [size * 0.7, size * 0.075], row)
+}
 
 /// Handles events for chess game.
 pub struct GameboardController {
@@ -16,13 +107,89 @@ pub struct GameboardController {
     pub is_check: bool,
     pub is_checkmate: bool,
     pub animation: MoveAnimation,
-    cursor_pos: [f64; 2]
+    /// The cell whose piece is being dragged with the mouse.
+    pub dragging: Option<BoardPos>,
+    /// Arrows drawn with the right mouse button, from and to.
+    pub arrows: Vec<(BoardPos, BoardPos)>,
+    /// Squares circled with the right mouse button.
+    pub circles: Vec<BoardPos>,
+    annotation_start: Option<BoardPos>,
+    /// First move pair shown in the move list.
+    pub move_list_scroll: usize,
+    move_list_rect: [f64; 4],
+    move_list_row_height: f64,
+    /// The square focused by the keyboard cursor, `None` while it is hidden.
+    pub keyboard_cursor: Option<BoardPos>,
+    /// Shows the board from Black's side when set.
+    pub flipped: bool,
+    /// Turns the board towards the side to move after every move.
+    pub auto_flip: bool,
+    /// Shows the a-h and 1-8 labels around the board.
+    pub show_coordinates: bool,
+    /// Index of the piece set in use.
+    pub piece_set: usize,
+    /// Names of the piece sets that can be chosen with P.
+    pub piece_set_names: Vec<String>,
+    /// Index of the colour theme in use.
+    pub theme: usize,
+    /// Names of the themes that can be chosen with T.
+    pub theme_names: Vec<String>,
+    /// The game clock, `None` for untimed games.
+    pub clock: Option<ChessClock>,
+    /// How the game ended, `None` while it is in progress.
+    pub result: Option<GameResult>,
+    /// Number of moves played when the game ended.
+    result_ply: usize,
+    /// The side that has offered a draw which has not been answered yet.
+    pub draw_offer: Option<board::Color>,
+    /// The open new game dialog, which takes all input while shown.
+    pub new_game_dialog: Option<NewGameDialog>,
+    /// Whether the clock was running when the new game dialog paused it.
+    paused_clock: bool,
+    /// Whether finished games are saved as PGN.
+    pub save_finished_games: bool,
+    /// Path of the PGN file the finished game was saved to, or why saving failed.
+    pub saved_game: Option<Result<String, String>>,
+    /// Text typed into the move input line, `None` when the line is not focused.
+    pub move_input: Option<String>,
+    /// Why the last typed move was rejected.
+    pub move_input_error: Option<String>,
+    /// Legal moves starting with the typed text.
+    pub move_suggestions: Vec<String>,
+    time_control: usize,
+    /// The side played by the computer, `None` when both sides are human.
+    pub engine_color: Option<board::Color>,
+    /// Index into `STRENGTH_LEVELS`.
+    pub engine_strength: usize,
+    /// Seed for the computer's choice between equally good moves.
+    pub engine_seed: u64,
+    engine_search: Option<EngineSearch>,
+    /// External UCI engine used instead of the built-in one when connected.
+    pub uci_engine: Option<UciEngine>,
+    /// Lets the UCI engine analyse the position while humans are to move.
+    pub analysing: bool,
+    /// Stores every position of the game for undo and redo.
+    pub history: GameHistory,
+    /// The PGN file last saved or loaded, opened again with Ctrl+O.
+    pub pgn_path: Option<String>,
+    cursor_pos: [f64; 2],
+    ctrl_held: bool,
+    pending_move: Option<(BoardPos, BoardPos)>,
+    /// Time source for animations and the game clock.
+    pub time: Rc<dyn Clock>
 }
 
 impl GameboardController {
     /// Creates a new gameboard controller.
     pub fn new(gameboard: Game) -> GameboardController {
+        GameboardController::with_clock(gameboard, Rc::new(MonotonicClock::new()))
+    }
+
+    /// Creates a new gameboard controller that measures animations and game
+    /// clocks with `time`.
+    pub fn with_clock(gameboard: Game, time: Rc<dyn Clock>) -> GameboardController {
         GameboardController {
+            history: GameHistory::new(gameboard.clone()),
             gameboard: gameboard,
             selected_cell: None,
             selected_cell_moves: HashSet::new(),
@@ -30,8 +197,45 @@ impl GameboardController {
             hovered_promotion_square: None,
             is_check: false,
             is_checkmate: false,
-            animation: MoveAnimation::new(),
+            animation: MoveAnimation::new(time.clone()),
+            dragging: None,
+            arrows: Vec::new(),
+            circles: Vec::new(),
+            annotation_start: None,
+            move_list_scroll: 0,
+            move_list_rect: [0.0; 4],
+            move_list_row_height: 1.0,
+            keyboard_cursor: None,
+            flipped: false,
+            auto_flip: false,
+            show_coordinates: true,
+            piece_set: 0,
+            piece_set_names: vec![String::from("font")],
+            theme: 0,
+            theme_names: vec![String::from("original")],
+            clock: None,
+            result: None,
+            result_ply: 0,
+            draw_offer: None,
+            new_game_dialog: None,
+            paused_clock: false,
+            save_finished_games: true,
+            saved_game: None,
+            move_input: None,
+            move_input_error: None,
+            move_suggestions: Vec::new(),
+            time_control: 0,
+            engine_color: None,
+            engine_strength: 2,
+            engine_seed: current_time(),
+            engine_search: None,
+            uci_engine: None,
+            analysing: false,
+            pgn_path: None,
             cursor_pos: [0.0; 2],
+            ctrl_held: false,
+            pending_move: None,
+            time,
         }
     }
 
@@ -39,18 +243,22 @@ impl GameboardController {
     pub fn event<E: GenericEvent>(&mut self, pos: [f64; 2], size: f64, e: &E) {
         use piston::input::{Button, Key, MouseButton};
 
+        self.animation.update();
+        if self.new_game_dialog.is_none() {
+            self.update_clock();
+            self.update_engine();
+        }
+
         if let Some(cursor_pos) = e.mouse_cursor_args() {
             self.cursor_pos = cursor_pos;
 
-            if let Some(promotion_square) = self.ongoing_promotion.clone() {
+            if let Some(menu_origin) = self.promotion_menu_origin() {
                 let square_x = (cursor_pos[0] - pos[0]) / size * 8.0;
                 let square_y = (cursor_pos[1] - pos[1]) / size * 8.0;
-                let pro_menu_min = promotion_square.file() as f64 - 1.5;
+                let pro_menu_min = menu_origin[0];
 
-                //Opposite color because api swaps turn before promotion
                 if square_x >= pro_menu_min && square_x < pro_menu_min + 4.0  && 
-                    ((self.gameboard.current_turn() != board::Color::White && square_y >= -1.0 && square_y < 0.0) ||
-                     (self.gameboard.current_turn() != board::Color::Black && square_y >=  8.0 && square_y < 9.0)) { 
+                    square_y >= menu_origin[1] && square_y < menu_origin[1] + 1.0 { 
                         
                     self.hovered_promotion_square = Some((square_x - pro_menu_min) as usize);
                 }
@@ -60,58 +268,54 @@ impl GameboardController {
             }
         }
 
+        // Modifiers are tracked even while the dialog or move input takes the
+        // keyboard, so a release there does not leave Ctrl held.
+        match e.press_args() {
+            Some(Button::Keyboard(Key::LCtrl)) | Some(Button::Keyboard(Key::RCtrl)) => self.ctrl_held = true,
+            _ => {}
+        }
+        match e.release_args() {
+            Some(Button::Keyboard(Key::LCtrl)) | Some(Button::Keyboard(Key::RCtrl)) => self.ctrl_held = false,
+            _ => {}
+        }
+
+        if self.new_game_dialog.is_some() {
+            self.new_game_dialog_event(pos, size, e);
+            return;
+        }
+        if self.move_input.is_some() {
+            self.move_input_event(e);
+            return;
+        }
+
+        if let Some(Button::Mouse(MouseButton::Right)) = e.press_args() {
+            self.annotation_start = self.cell_at(pos, size, self.cursor_pos);
+        }
+
+        if let Some(Button::Mouse(MouseButton::Right)) = e.release_args() {
+            if let (Some(start), Some(end)) = (self.annotation_start.take(), self.cell_at(pos, size, self.cursor_pos)) {
+                self.toggle_annotation(start, end);
+            }
+        }
+
         if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
-            // Find coordinates relative to upper left corner.
-            let x = self.cursor_pos[0] - pos[0];
-            let y = self.cursor_pos[1] - pos[1];
-            
+            self.arrows.clear();
+            self.circles.clear();
+
+            self.keyboard_cursor = None;
+
             if self.ongoing_promotion.is_some() {
                 if let Some(hovered_square) = self.hovered_promotion_square {
-                    let promotion = match hovered_square {
-                        0 => PieceType::Knight,
-                        1 => PieceType::Bishop,
-                        2 => PieceType::Rook,
-                        3 => PieceType::Queen,
-                        _ => PieceType::Queen
-                    };
-                    self.gameboard.promote(promotion);
-                    self.ongoing_promotion = None;
-                    self.hovered_promotion_square = None;
+                    self.choose_promotion(PROMOTION_PIECES[hovered_square.min(3)]).ok();
                 }
             }
             else {  
-                if x >= 0.0 && x < size && y >= 0.0 && y < size {
-                    let file = (x / size * 8.0) as u8;
-                    let rank = (y / size * 8.0) as u8;
-                    let clicked_cell = BoardPos::new(file, 7 - rank);
-        
-                    if let Some(selected_cell) = self.selected_cell.clone() {
-                        if selected_cell != clicked_cell {
-                            
-                            if let Some(tile) = self.gameboard.get_tile(&clicked_cell) {
-
-                                if tile.color() == self.gameboard.current_turn() {
-                                    self.selected_cell = Some(clicked_cell);
-                                    self.update_selected_cell_moves();
-                                }
-                                else {
-                                    self.check_selected_move(selected_cell, clicked_cell);
-                                    self.selected_cell = None;
-                                    self.selected_cell_moves.drain();
-                                }
-                            }
-                            else {
-                                self.check_selected_move(selected_cell, clicked_cell);
-                                self.selected_cell = None;
-                                self.selected_cell_moves.drain();
-                            }
-                        }
-                    }
-                    else if let Some(tile) = self.gameboard.get_tile(&clicked_cell) {
-                        if tile.color() == self.gameboard.current_turn() {
-                            self.selected_cell = Some(clicked_cell);
-                            self.update_selected_cell_moves();
-                        }
+                if let Some(clicked_cell) = self.cell_at(pos, size, self.cursor_pos) {
+                    self.select(clicked_cell.clone()).ok();
+
+                    // Pressing on the selected piece picks it up for dragging.
+                    if self.selected_cell == Some(clicked_cell.clone()) {
+                        self.dragging = Some(clicked_cell);
                     }
                 }
                 else {
@@ -121,20 +325,1052 @@ impl GameboardController {
             }
         }
         
+        if let Some(Button::Mouse(MouseButton::Left)) = e.release_args() {
+            if let Some(dragged_cell) = self.dragging.take() {
+                if let Some(target) = self.cell_at(pos, size, self.cursor_pos) {
+                    // Dropping on an illegal square leaves the piece where it was.
+                    if target != dragged_cell && self.try_move(dragged_cell, target.clone()).is_ok() {
+                        // The dropped piece is already in place, anything it moved or captured still animates.
+                        self.animation.cancel_animation(&target);
+                        self.selected_cell = None;
+                        self.selected_cell_moves.drain();
+                    }
+                }
+            }
+        }
+
         if let Some(Button::Keyboard(key)) = e.press_args() {
+            // Arrow keys drive the keyboard cursor once it is shown; Up, Down or
+            // K show it, K hides it again.
+            let cursor_shown = self.keyboard_cursor.is_some() || self.ongoing_promotion.is_some();
+            if cursor_shown && key != Key::K && self.keyboard_cursor_key(key) {
+                return;
+            }
+
             match key {
-                Key::R => {
-                    self.gameboard = Game::new();
-                    self.ongoing_promotion = None;
-                    self.selected_cell = None;
-                    self.selected_cell_moves.drain();
-                    self.is_check = false;
-                    self.is_checkmate = false;
-                    self.animation.cancel_current_animation();
+                Key::Up | Key::Down => self.move_keyboard_cursor(0, 0),
+                Key::K => {
+                    if self.keyboard_cursor.take().is_none() {
+                        self.move_keyboard_cursor(0, 0);
+                    }
+                },
+                Key::Slash => self.focus_move_input(),
+                Key::Z if self.ctrl_held => self.undo(),
+                Key::Y if self.ctrl_held => self.redo(),
+                Key::S if self.ctrl_held => match self.save_game() {
+                    Ok(path) => println!("Saved game to {}", path),
+                    Err(err) => println!("Could not save game: {}", err),
+                },
+                Key::O if self.ctrl_held => match self.pgn_path.clone().or_else(last_saved_game) {
+                    Some(path) => match self.open_game(&path) {
+                        Ok(moves) => println!("Loaded {} ({} moves)", path, moves),
+                        Err(err) => println!("Could not load {}: {}", path, err),
+                    },
+                    None => println!("No saved game to open"),
+                },
+                Key::F if self.ctrl_held => match self.save_position() {
+                    Ok(fen) => println!("{}", fen),
+                    Err(err) => println!("Could not save position: {}", err),
+                },
+                Key::L if self.ctrl_held => match read_fen(POSITION_FILE).and_then(|fen| self.open_position(fen)) {
+                    Ok(()) => println!("Loaded {}", POSITION_FILE),
+                    Err(err) => println!("Could not load {}: {}", POSITION_FILE, err),
+                },
+                Key::V if self.ctrl_held => {
+                    let pasted = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text());
+                    match pasted {
+                        Ok(text) => match Fen::parse(text.trim()).and_then(|fen| self.open_position(fen)) {
+                            Ok(()) => println!("Loaded position {}", text.trim()),
+                            Err(err) => println!("Could not load pasted position: {}", err),
+                        },
+                        Err(err) => println!("Could not read the clipboard: {}", err),
+                    }
+                },
+                Key::F => self.flipped = !self.flipped,
+                Key::A => {
+                    self.auto_flip = !self.auto_flip;
+                    self.update_game_state();
+                },
+                Key::Left => self.step_back(),
+                Key::Right => self.step_forward(),
+                Key::Home => self.go_to_move(0),
+                Key::End => self.go_to_move(self.history.moves().len()),
+                Key::E => {
+                    self.engine_color = match self.engine_color {
+                        None => Some(board::Color::Black),
+                        Some(board::Color::Black) => Some(board::Color::White),
+                        Some(board::Color::White) => None,
+                    };
+                    self.engine_search = None;
+                },
+                Key::D1 | Key::D2 | Key::D3 | Key::D4 | Key::D5 => {
+                    self.engine_strength = key as usize - Key::D1 as usize;
+                    self.engine_search = None;
+                },
+                Key::G => self.perform_action(GameAction::Resign),
+                Key::D if self.has_draw_offer_to_answer() => self.perform_action(GameAction::AcceptDraw),
+                Key::D => self.perform_action(GameAction::OfferDraw),
+                Key::X => self.perform_action(GameAction::DeclineDraw),
+                Key::C => self.show_coordinates = !self.show_coordinates,
+                Key::P => {
+                    self.piece_set = (self.piece_set + 1) % self.piece_set_names.len();
+                    println!("Piece set: {}", self.piece_set_names[self.piece_set]);
+                },
+                Key::M => {
+                    let index = ANIMATION_DURATIONS.iter().position(|duration| *duration == self.animation.duration).unwrap_or(0);
+                    self.animation.duration = ANIMATION_DURATIONS[(index + 1) % ANIMATION_DURATIONS.len()];
+                    match self.animation.duration {
+                        0 => println!("Animations off"),
+                        duration => println!("Animation duration: {} ms", duration),
+                    }
+                },
+                Key::T => {
+                    self.theme = (self.theme + 1) % self.theme_names.len();
+                    println!("Theme: {}", self.theme_names[self.theme]);
+                },
+                Key::N => {
+                    self.analysing = !self.analysing;
+                    self.cancel_engine_search();
+                },
+                Key::R => self.open_new_game_dialog(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Starts typing a move into the input line below the move list.
+    pub fn focus_move_input(&mut self) {
+        self.move_input = Some(String::new());
+        self.move_input_error = None;
+        self.update_move_suggestions();
+    }
+
+    /// Handles typing while the move input line is focused. `/` leaves the line.
+    fn move_input_event<E: GenericEvent>(&mut self, e: &E) {
+        use piston::input::{Button, Key};
+
+        if let Some(text) = e.text_args() {
+            if let Some(input) = self.move_input.as_mut() {
+                input.extend(text.chars().filter(|c| !c.is_whitespace() && *c != '/'));
+            }
+            self.move_input_error = None;
+            self.update_move_suggestions();
+        }
+
+        match e.press_args() {
+            Some(Button::Keyboard(Key::Backspace)) => {
+                if let Some(input) = self.move_input.as_mut() {
+                    input.pop();
                 }
+                self.move_input_error = None;
+                self.update_move_suggestions();
+            },
+            Some(Button::Keyboard(Key::Tab)) => self.complete_move_input(),
+            Some(Button::Keyboard(Key::Slash)) => self.move_input = None,
+            Some(Button::Keyboard(Key::Return)) => self.submit_move_input(),
+            _ => {}
+        }
+    }
+
+    fn update_move_suggestions(&mut self) {
+        let input = self.move_input.clone().unwrap_or_default();
+        self.move_suggestions = move_names(&self.gameboard).into_iter()
+            .map(|(san, uci, _)| if san.starts_with(&input) || !uci.starts_with(&input) { san } else { uci })
+            .filter(|name| name.starts_with(&input))
+            .collect();
+        self.move_suggestions.sort();
+    }
+
+    /// Completes the typed text as far as all matching moves agree.
+    fn complete_move_input(&mut self) {
+        let first = match self.move_suggestions.first() {
+            Some(first) => first.clone(),
+            None => return,
+        };
+        let mut prefix_len = first.len();
+        for suggestion in &self.move_suggestions[1..] {
+            prefix_len = first.chars().zip(suggestion.chars())
+                .take(prefix_len)
+                .take_while(|(a, b)| a == b)
+                .count();
+        }
+        self.move_input = Some(first[..prefix_len].to_string());
+        self.update_move_suggestions();
+    }
+
+    /// Plays the typed move in SAN or UCI notation.
+    fn submit_move_input(&mut self) {
+        let input = self.move_input.clone().unwrap_or_default();
+        if input.is_empty() {
+            return;
+        }
+
+        let mv = parse_move(&self.gameboard, &input).map_err(|err| err.to_string());
+
+        let result = match mv {
+            Ok(_) if self.result.is_some() => Err(String::from("the game is over")),
+            Ok(_) if self.is_engine_turn() => Err(String::from("it is the computer's turn")),
+            Ok(mv) => {
+                if self.play_move(&mv) { Ok(()) } else { Err(String::from("could not play move")) }
+            },
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok(()) => {
+                self.move_input = Some(String::new());
+                self.move_input_error = None;
+            },
+            Err(err) => self.move_input_error = Some(format!("{}: {}", input, err)),
+        }
+        self.update_move_suggestions();
+    }
+
+    /// Shows the new game dialog, preset with the current game's settings.
+    pub fn open_new_game_dialog(&mut self) {
+        let (side, opponent) = match self.engine_color {
+            Some(color) => (opponent(color), Opponent::Computer),
+            None => (board::Color::White, Opponent::Human),
+        };
+        let in_progress = self.result.is_none() && !self.history.moves().is_empty();
+        self.dragging = None;
+        self.new_game_dialog = Some(NewGameDialog::new(side, opponent, self.time_control, in_progress));
+
+        // The game is paused while the dialog is open.
+        self.cancel_engine_search();
+        let now = self.time.now();
+        if let Some(clock) = self.clock.as_mut() {
+            self.paused_clock = clock.is_running();
+            clock.stop(now);
+        }
+    }
+
+    /// Closes the new game dialog and resumes the game.
+    fn close_new_game_dialog(&mut self) {
+        self.new_game_dialog = None;
+        let now = self.time.now();
+        if let Some(clock) = self.clock.as_mut() {
+            if self.paused_clock {
+                clock.start(now, self.gameboard.current_turn());
+            }
+        }
+        self.paused_clock = false;
+    }
+
+    fn new_game_dialog_event<E: GenericEvent>(&mut self, pos: [f64; 2], size: f64, e: &E) {
+        use piston::input::{Button, Key, MouseButton};
+
+        let mut activated_row = None;
+        if let Some(dialog) = self.new_game_dialog.as_mut() {
+            match e.press_args() {
+                Some(Button::Keyboard(Key::Up)) =>
+                    dialog.focused_row = (dialog.focused_row + DIALOG_ROWS - 1) % DIALOG_ROWS,
+                Some(Button::Keyboard(Key::Down)) =>
+                    dialog.focused_row = (dialog.focused_row + 1) % DIALOG_ROWS,
+                Some(Button::Keyboard(Key::Left)) => dialog.cycle(dialog.focused_row, false),
+                Some(Button::Keyboard(Key::Right)) => dialog.cycle(dialog.focused_row, true),
+                Some(Button::Keyboard(Key::Return)) => activated_row = Some(dialog.focused_row),
+                Some(Button::Keyboard(Key::Backspace)) | Some(Button::Keyboard(Key::Escape)) => activated_row = Some(CANCEL_ROW),
+                Some(Button::Mouse(MouseButton::Left)) => {
+                    for row in 0..DIALOG_ROWS {
+                        let rect = dialog_row_rect(pos, size, row);
+                        if self.cursor_pos[0] >= rect[0] && self.cursor_pos[0] < rect[0] + rect[2] &&
+                            self.cursor_pos[1] >= rect[1] && self.cursor_pos[1] < rect[1] + rect[3] {
+                            dialog.focused_row = row;
+                            activated_row = Some(row);
+                        }
+                    }
+                },
                 _ => {}
             }
         }
+
+        match activated_row {
+            Some(START_ROW) => self.start_new_game(),
+            Some(CANCEL_ROW) => self.close_new_game_dialog(),
+            Some(row) => {
+                if let Some(dialog) = self.new_game_dialog.as_mut() {
+                    dialog.cycle(row, true);
+                }
+            },
+            None => {}
+        }
+    }
+
+    /// Closes the new game dialog and starts a game with the chosen options.
+    /// The dialog stays open if the saved position cannot be loaded.
+    fn start_new_game(&mut self) {
+        let start = match self.new_game_dialog.as_ref() {
+            Some(dialog) => dialog.start,
+            None => return,
+        };
+        let history = match start {
+            StartPosition::Standard => GameHistory::new(Game::new()),
+            StartPosition::CurrentPosition => {
+                let fen = Fen::at_ply(&self.history, self.history.current());
+                GameHistory::from_fen(self.history.current_position(), fen)
+            },
+            StartPosition::SavedPosition => {
+                match read_fen(POSITION_FILE).and_then(|fen| fen.to_game().map(|game| GameHistory::from_fen(game, fen))) {
+                    Ok(history) => history,
+                    Err(err) => {
+                        println!("Could not load {}: {}", POSITION_FILE, err);
+                        return;
+                    }
+                }
+            },
+        };
+        let dialog = match self.new_game_dialog.take() {
+            Some(dialog) => dialog,
+            None => return,
+        };
+
+        self.time_control = dialog.time_control;
+        self.engine_color = match dialog.opponent {
+            Opponent::Human => None,
+            Opponent::Computer => Some(opponent(dialog.side)),
+        };
+        if !self.auto_flip {
+            self.flipped = dialog.opponent == Opponent::Computer && dialog.side == board::Color::Black;
+        }
+        self.paused_clock = false;
+        self.start_game(history);
+    }
+
+    /// Starts a new game from the initial position with a fresh clock.
+    pub fn reset(&mut self) {
+        self.start_game(GameHistory::new(Game::new()));
+    }
+
+    /// Starts a new game from a FEN position with a fresh clock.
+    pub fn open_position(&mut self, fen: Fen) -> Result<(), FenError> {
+        let game = fen.to_game()?;
+        self.start_game(GameHistory::from_fen(game, fen));
+        Ok(())
+    }
+
+    fn start_game(&mut self, history: GameHistory) {
+        let game = history.current_position();
+        self.history = history;
+        self.clock = TIME_CONTROLS[self.time_control].map(ChessClock::new);
+        self.result = None;
+        self.saved_game = None;
+        self.draw_offer = None;
+        self.restore_position(game);
+    }
+
+    /// Returns the actions currently available as buttons.
+    pub fn available_actions(&self) -> Vec<GameAction> {
+        if self.result.is_some() {
+            Vec::new()
+        }
+        else if self.has_draw_offer_to_answer() {
+            vec![GameAction::Resign, GameAction::AcceptDraw, GameAction::DeclineDraw]
+        }
+        else {
+            vec![GameAction::Resign, GameAction::OfferDraw]
+        }
+    }
+
+    /// Returns the side the human acts for, which is the side to move in
+    /// hot-seat games.
+    fn player(&self) -> board::Color {
+        match self.engine_color {
+            Some(color) => opponent(color),
+            None => self.gameboard.current_turn(),
+        }
+    }
+
+    /// Returns whether the other side has offered the human player a draw.
+    fn has_draw_offer_to_answer(&self) -> bool {
+        self.draw_offer.is_some() && self.draw_offer != Some(self.player())
+    }
+
+    /// Handles clicks on the action buttons laid out by `button_rect`.
+    pub fn button_event<E: GenericEvent>(&mut self, position: [f64; 2], size: [f64; 2], e: &E) {
+        use piston::input::{Button, MouseButton};
+
+        if self.new_game_dialog.is_some() {
+            return;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            let cursor = self.cursor_pos;
+            for (index, action) in self.available_actions().into_iter().enumerate() {
+                let rect = button_rect(position, size, index);
+                if cursor[0] >= rect[0] && cursor[0] < rect[0] + rect[2] &&
+                    cursor[1] >= rect[1] && cursor[1] < rect[1] + rect[3] {
+                    self.perform_action(action);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Resigns, or offers, accepts or declines a draw on behalf of the human
+    /// player, who is the side to move in hot-seat games.
+    pub fn perform_action(&mut self, action: GameAction) {
+        if self.result.is_some() {
+            return;
+        }
+        let player = self.player();
+        let answers_offer = self.has_draw_offer_to_answer();
+
+        match action {
+            GameAction::Resign => self.finish_game(GameResult::win(opponent(player), Termination::Resignation)),
+            GameAction::OfferDraw => {
+                self.draw_offer = Some(player);
+                if self.engine_color.is_some() {
+                    self.answer_draw_offer_as_engine();
+                }
+            },
+            GameAction::AcceptDraw if answers_offer => {
+                self.draw_offer = None;
+                self.finish_game(GameResult::draw(Termination::Agreement));
+            },
+            GameAction::DeclineDraw if answers_offer => self.draw_offer = None,
+            GameAction::AcceptDraw | GameAction::DeclineDraw => {},
+        }
+    }
+
+    /// The computer accepts a draw only when it is clearly worse.
+    fn answer_draw_offer_as_engine(&mut self) {
+        let engine_color = match self.engine_color {
+            Some(color) => color,
+            None => return,
+        };
+        let mut score = evaluate(&self.gameboard);
+        if self.gameboard.current_turn() != engine_color {
+            score = -score;
+        }
+
+        if score < -150 {
+            println!("The computer accepts the draw");
+            self.draw_offer = None;
+            self.finish_game(GameResult::draw(Termination::Agreement));
+        }
+        else {
+            println!("The computer declines the draw");
+            self.draw_offer = None;
+        }
+    }
+
+    /// Ends the game, stopping the clock and, with `save_finished_games`,
+    /// saving it as PGN.
+    pub fn finish_game(&mut self, result: GameResult) {
+        if self.result.is_some() {
+            return;
+        }
+        if let Some(clock) = self.clock.as_mut() {
+            clock.stop(self.time.now());
+        }
+        self.result = Some(result);
+        self.result_ply = self.history.current();
+        self.ongoing_promotion = None;
+        self.pending_move = None;
+        self.dragging = None;
+        self.selected_cell = None;
+        self.selected_cell_moves.drain();
+        self.cancel_engine_search();
+        if self.save_finished_games {
+            let saved = self.save_game();
+            self.saved_game = Some(saved.map_err(|err| err.to_string()));
+        }
+    }
+
+    /// Returns whether the result overlay should be shown, which is only at
+    /// the final position so the game can still be reviewed.
+    pub fn show_result(&self) -> bool {
+        self.result.is_some() && self.history.current() == self.result_ply
+    }
+
+    /// Ends the game if the running clock has reached zero.
+    fn update_clock(&mut self) {
+        let now = self.time.now();
+        let flagged = self.clock.as_ref().and_then(|clock| clock.flagged(now));
+        if let Some(color) = flagged {
+            // Judge material on the final position, not one being reviewed.
+            let result = outcome::timeout(self.history.position(self.history.moves().len()), color);
+            self.finish_game(result);
+        }
+    }
+
+    /// Returns whether the computer is to move.
+    pub fn is_engine_turn(&self) -> bool {
+        self.engine_color.is_some() && self.engine_color == Some(self.gameboard.current_turn())
+    }
+
+    /// Returns whether the computer is searching for a move.
+    pub fn is_engine_thinking(&self) -> bool {
+        self.engine_search.is_some()
+    }
+
+    /// Starts the UCI engine at `path` and uses it for computer moves and analysis.
+    pub fn connect_engine(&mut self, path: &str) {
+        match UciEngine::spawn(path) {
+            Ok(engine) => {
+                println!("Connected to {}", engine.name);
+                self.uci_engine = Some(engine);
+            },
+            Err(err) => println!("Could not start engine {}: {}", path, err),
+        }
+    }
+
+    /// Abandons any search for a position that is no longer on the board.
+    fn cancel_engine_search(&mut self) {
+        self.engine_search = None;
+        if let Some(engine) = self.uci_engine.as_mut() {
+            engine.stop().ok();
+        }
+    }
+
+    /// Starts a search when it is the computer's turn and plays its move once found.
+    fn update_engine(&mut self) {
+        let game_over = self.result.is_some();
+        let engine_to_move = self.is_engine_turn() && !game_over && self.ongoing_promotion.is_none();
+
+        if self.uci_engine.is_some() {
+            self.update_uci_engine(engine_to_move, game_over);
+            return;
+        }
+        if !engine_to_move {
+            self.engine_search = None;
+            return;
+        }
+
+        match self.engine_search.as_ref() {
+            None => {
+                let strength = STRENGTH_LEVELS[self.engine_strength];
+                let seed = self.engine_seed.wrapping_add(self.history.current() as u64);
+                self.engine_search = Some(EngineSearch::start(self.gameboard.clone(), strength, seed));
+            },
+            Some(search) => {
+                if !self.animation.is_running() {
+                    if let Some(result) = search.poll() {
+                        self.engine_search = None;
+                        if let Some(mv) = result {
+                            self.play_move(&mv);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_uci_engine(&mut self, engine_to_move: bool, game_over: bool) {
+        let moves: Vec<String> = self.history.moves()[..self.history.current()].iter()
+            .map(|record| to_uci(&record.from, &record.to, record.promotion))
+            .collect();
+        let movetime = STRENGTH_LEVELS[self.engine_strength].time_limit.unwrap_or(1_000);
+
+        let start_fen = self.start_fen();
+
+        let engine = match self.uci_engine.as_mut() {
+            Some(engine) => engine,
+            None => return,
+        };
+        let best = match engine.poll() {
+            Ok(best) => best,
+            Err(err) => {
+                println!("Lost connection to engine: {}", err);
+                self.uci_engine = None;
+                return;
+            }
+        };
+        if let Some(best) = best {
+            if engine_to_move && !self.animation.is_running() {
+                match parse_uci(&best) {
+                    Some(mv) if legal_moves(&self.gameboard).contains(&mv) => {
+                        self.play_move(&mv);
+                    },
+                    _ => println!("Engine sent an illegal move: {}", best),
+                }
+            }
+            return;
+        }
+
+        if !engine.is_searching() && !self.animation.is_running() {
+            let result = if engine_to_move {
+                engine.go(&start_fen, &moves, Some(movetime))
+            }
+            else if self.analysing && !game_over {
+                engine.go(&start_fen, &moves, None)
+            }
+            else {
+                Ok(())
+            };
+            if let Err(err) = result {
+                println!("Lost connection to engine: {}", err);
+                self.uci_engine = None;
+            }
+        }
+    }
+
+    /// Plays a legal move that did not come from the mouse, choosing a queen
+    /// when a promotion piece is needed but not given.
+    pub fn play_move(&mut self, mv: &Move) -> bool {
+        if self.result.is_some() || self.ongoing_promotion.is_some() {
+            return false;
+        }
+        let before = self.gameboard.clone();
+        if self.gameboard.move_piece(&mv.from, &mv.to).is_err() {
+            return false;
+        }
+
+        let mut promotion = None;
+        if let GameState::PromotionRequired(_) = self.gameboard.get_state() {
+            let piece = mv.promotion.unwrap_or(PieceType::Queen);
+            self.gameboard.promote(piece);
+            promotion = Some(piece);
+        }
+
+        self.selected_cell = None;
+        self.selected_cell_moves.drain();
+        self.dragging = None;
+        self.animation.cancel_animations();
+        self.animation.animate_move(&before, &self.gameboard);
+        self.record_move(mv.from.clone(), mv.to.clone(), promotion);
+        true
+    }
+
+    /// Adds a circle (when `start == end`) or an arrow, or removes it if it is already drawn.
+    fn toggle_annotation(&mut self, start: BoardPos, end: BoardPos) {
+        if start == end {
+            match self.circles.iter().position(|square| *square == start) {
+                Some(index) => { self.circles.remove(index); },
+                None => self.circles.push(start),
+            }
+        }
+        else {
+            match self.arrows.iter().position(|(from, to)| *from == start && *to == end) {
+                Some(index) => { self.arrows.remove(index); },
+                None => self.arrows.push((start, end)),
+            }
+        }
+    }
+
+    /// Returns the pieces captured so far, most valuable first: index 0 holds
+    /// the Black pieces taken by White and index 1 the White pieces taken by Black.
+    pub fn captured_pieces(&self) -> [Vec<PieceType>; 2] {
+        let mut captured = [Vec::new(), Vec::new()];
+        for (ply, record) in self.history.moves()[..self.history.current()].iter().enumerate() {
+            if let Some(piece) = record.captured {
+                captured[ply % 2].push(piece);
+            }
+        }
+        for pieces in captured.iter_mut() {
+            pieces.sort_by_key(|piece| -piece_value(*piece));
+        }
+        captured
+    }
+
+    /// Returns White's material advantage on the board in pawns, negative when Black is ahead.
+    pub fn material_balance(&self) -> i32 {
+        let mut balance = 0;
+        for rank in 0..8 {
+            for file in 0..8 {
+                if let Some(tile) = self.gameboard.get_tile(&BoardPos::new(file, rank)) {
+                    let value = piece_value(tile.piece()) / 100;
+                    match tile.color() {
+                        board::Color::White => balance += value,
+                        board::Color::Black => balance -= value,
+                    }
+                }
+            }
+        }
+        balance
+    }
+
+    /// Selects the piece of the side to move on `cell`, or moves the selected
+    /// piece there. Clicking a square calls this.
+    pub fn select(&mut self, cell: BoardPos) -> Result<CommandOutcome, CommandError> {
+        if self.result.is_some() {
+            return Err(CommandError::GameOver);
+        }
+        if self.ongoing_promotion.is_some() {
+            return Err(CommandError::PromotionPending);
+        }
+
+        let own_piece = self.gameboard.get_tile(&cell)
+            .is_some_and(|tile| tile.color() == self.gameboard.current_turn());
+        if own_piece {
+            self.selected_cell = Some(cell);
+            self.update_selected_cell_moves();
+            return Ok(CommandOutcome::Selected);
+        }
+
+        match self.selected_cell.take() {
+            Some(selected_cell) => {
+                self.selected_cell_moves.drain();
+                self.try_move(selected_cell, cell)
+            },
+            None => Ok(CommandOutcome::Deselected),
+        }
+    }
+
+    /// Completes a pending promotion with the given piece.
+    pub fn choose_promotion(&mut self, promotion: PieceType) -> Result<CommandOutcome, CommandError> {
+        if self.ongoing_promotion.is_none() {
+            return Err(CommandError::NoPromotionPending);
+        }
+        if !PROMOTION_PIECES.contains(&promotion) {
+            return Err(CommandError::InvalidPromotion);
+        }
+        self.gameboard.promote(promotion);
+        self.ongoing_promotion = None;
+        self.hovered_promotion_square = None;
+
+        if let Some((from, to)) = self.pending_move.take() {
+            self.record_move(from, to, Some(promotion));
+        }
+        Ok(self.moved())
+    }
+
+    /// Moves the keyboard cursor one square in a screen direction, showing it
+    /// at the selected piece or the centre of the board first.
+    fn move_keyboard_cursor(&mut self, columns: i32, rows: i32) {
+        let cursor = match self.keyboard_cursor.clone() {
+            Some(cursor) => cursor,
+            None => {
+                let start = self.selected_cell.clone().unwrap_or(BoardPos::new(4, 3));
+                self.keyboard_cursor = Some(start);
+                return;
+            }
+        };
+
+        let (files, ranks) = if self.flipped { (-columns, rows) } else { (columns, -rows) };
+        let file = (cursor.file() as i32 + files).clamp(0, 7);
+        let rank = (cursor.rank() as i32 + ranks).clamp(0, 7);
+        self.keyboard_cursor = Some(BoardPos::new(file as u8, rank as u8));
+    }
+
+    /// Handles the arrow keys and Enter/Space while the keyboard cursor is
+    /// shown. Returns whether the key was used.
+    fn keyboard_cursor_key(&mut self, key: piston::input::Key) -> bool {
+        use piston::input::Key;
+
+        if self.ongoing_promotion.is_some() {
+            let hovered = self.hovered_promotion_square.unwrap_or(3);
+            match key {
+                Key::Left => self.hovered_promotion_square = Some(hovered.saturating_sub(1)),
+                Key::Right => self.hovered_promotion_square = Some((hovered + 1).min(3)),
+                Key::Return | Key::Space => {
+                    self.choose_promotion(PROMOTION_PIECES[hovered]).ok();
+                },
+                _ => return false,
+            }
+            return true;
+        }
+
+        match key {
+            Key::Up => self.move_keyboard_cursor(0, -1),
+            Key::Down => self.move_keyboard_cursor(0, 1),
+            Key::Left => self.move_keyboard_cursor(-1, 0),
+            Key::Right => self.move_keyboard_cursor(1, 0),
+            Key::Return | Key::Space => {
+                if let Some(cursor) = self.keyboard_cursor.clone() {
+                    self.select(cursor).ok();
+                    if self.ongoing_promotion.is_some() {
+                        self.hovered_promotion_square = Some(3);
+                    }
+                }
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns the current mouse cursor position.
+    pub fn cursor_pos(&self) -> [f64; 2] {
+        self.cursor_pos
+    }
+
+    /// Finds the cell under a point in window coordinates.
+    fn cell_at(&self, pos: [f64; 2], size: f64, point: [f64; 2]) -> Option<BoardPos> {
+        // Find coordinates relative to upper left corner.
+        let x = point[0] - pos[0];
+        let y = point[1] - pos[1];
+
+        if x >= 0.0 && x < size && y >= 0.0 && y < size {
+            let column = (x / size * 8.0) as u8;
+            let row = (y / size * 8.0) as u8;
+            if self.flipped {
+                return Some(BoardPos::new(7 - column, row));
+            }
+            return Some(BoardPos::new(column, 7 - row));
+        }
+        None
+    }
+
+    /// Converts board coordinates to screen cell coordinates, `[column, row]`
+    /// counted from the upper left corner of the board.
+    pub fn to_screen(&self, file: f64, rank: f64) -> [f64; 2] {
+        if self.flipped {
+            [7.0 - file, rank]
+        }
+        else {
+            [file, 7.0 - rank]
+        }
+    }
+
+    /// Returns the screen cell of the leftmost promotion menu entry. The menu is
+    /// shown just outside the board edge nearest to the promoting pawn.
+    pub fn promotion_menu_origin(&self) -> Option<[f64; 2]> {
+        let promotion_square = self.ongoing_promotion.clone()?;
+        let screen = self.to_screen(promotion_square.file() as f64, promotion_square.rank() as f64);
+        let row = if screen[1] < 4.0 { -1.0 } else { 8.0 };
+        Some([screen[0] - 1.5, row])
+    }
+
+    /// Takes back the last move, or the pending promotion move if there is
+    /// one. Against the computer its reply is taken back as well, so the
+    /// human is to move again. Taking back the move a finished game ended
+    /// on lets the game go on.
+    pub fn undo(&mut self) {
+        let pending_promotion = self.ongoing_promotion.is_some();
+        self.undo_move();
+        if !pending_promotion && self.is_engine_turn() {
+            self.undo_move();
+        }
+        if self.result.is_some() && self.history.current() < self.result_ply {
+            self.result = None;
+            self.saved_game = None;
+        }
+    }
+
+    /// Replays the last undone move, and the computer's reply to it.
+    pub fn redo(&mut self) {
+        self.redo_move();
+        if self.is_engine_turn() {
+            self.redo_move();
+        }
+    }
+
+    fn undo_move(&mut self) {
+        if self.ongoing_promotion.is_some() {
+            let position = self.history.current_position();
+            self.restore_position(position);
+        }
+        else if let Some(position) = self.history.undo() {
+            self.restore_position(position);
+        }
+    }
+
+    fn redo_move(&mut self) {
+        if self.ongoing_promotion.is_some() {
+            return;
+        }
+        if let Some(position) = self.history.redo() {
+            self.restore_position(position);
+        }
+    }
+
+    /// Steps one move back through the game, animating the piece returning.
+    pub fn step_back(&mut self) {
+        if self.ongoing_promotion.is_some() || self.history.current() == 0 {
+            return;
+        }
+        let before = self.gameboard.clone();
+        self.undo_move();
+        self.animation.animate_move(&before, &self.gameboard);
+    }
+
+    /// Steps one move forward through the game, animating the move.
+    pub fn step_forward(&mut self) {
+        if self.ongoing_promotion.is_some() || self.history.current() >= self.history.moves().len() {
+            return;
+        }
+        let before = self.gameboard.clone();
+        self.redo_move();
+        self.animation.animate_move(&before, &self.gameboard);
+    }
+
+    /// Handles clicks and scrolling in the move list. `rect` is the list area
+    /// as `[x, y, width, height]` and each row shows one move pair.
+    pub fn move_list_event<E: GenericEvent>(&mut self, rect: [f64; 4], row_height: f64, e: &E) {
+        use piston::input::{Button, MouseButton};
+
+        self.move_list_rect = rect;
+        self.move_list_row_height = row_height;
+
+        if self.new_game_dialog.is_some() {
+            return;
+        }
+        let x = self.cursor_pos[0] - rect[0];
+        let y = self.cursor_pos[1] - rect[1];
+        if x < 0.0 || x >= rect[2] || y < 0.0 || y >= rect[3] {
+            return;
+        }
+
+        if let Some(scroll) = e.mouse_scroll_args() {
+            let rows = (self.history.moves().len() + 1) / 2;
+            if scroll[1] > 0.0 {
+                self.move_list_scroll = self.move_list_scroll.saturating_sub(1);
+            }
+            else if scroll[1] < 0.0 && self.move_list_scroll + self.move_list_rows() < rows {
+                self.move_list_scroll += 1;
+            }
+        }
+
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            let row = (y / row_height) as usize + self.move_list_scroll;
+            // The number column and White's move share the left half of the row.
+            let ply = if x < rect[2] / 2.0 { row * 2 + 1 } else { row * 2 + 2 };
+            if ply <= self.history.moves().len() {
+                self.go_to_move(ply);
+            }
+        }
+    }
+
+    /// Shows the position after `ply` moves of the game.
+    pub fn go_to_move(&mut self, ply: usize) {
+        if self.ongoing_promotion.is_some() {
+            self.undo_move();
+        }
+        if let Some(position) = self.history.go_to(ply) {
+            self.restore_position(position);
+        }
+    }
+
+    fn move_list_rows(&self) -> usize {
+        ((self.move_list_rect[3] / self.move_list_row_height) as usize).max(1)
+    }
+
+    /// Scrolls the move list so the current move is visible.
+    fn scroll_to_current(&mut self) {
+        let row = self.history.current().saturating_sub(1) / 2;
+        let rows = self.move_list_rows();
+        if row < self.move_list_scroll {
+            self.move_list_scroll = row;
+        }
+        else if row >= self.move_list_scroll + rows {
+            self.move_list_scroll = row + 1 - rows;
+        }
+    }
+
+    /// Loads a PGN file and shows its starting position, ready to be stepped
+    /// through. The clock and the computer opponent are turned off so the
+    /// game can be replayed without either of them moving on. Returns the
+    /// number of moves in the game.
+    pub fn open_game(&mut self, path: &str) -> Result<usize, PgnError> {
+        let mut history = load_pgn(path, Game::new())?;
+        while history.undo().is_some() {}
+        let position = history.current_position();
+        self.history = history;
+        self.clock = None;
+        self.engine_color = None;
+        self.result = None;
+        self.saved_game = None;
+        self.pgn_path = Some(path.to_string());
+        self.restore_position(position);
+        Ok(self.history.moves().len())
+    }
+
+    /// Returns the FEN of the position on the board.
+    pub fn current_fen(&self) -> String {
+        Fen::from_game(&self.gameboard, &self.history).to_string()
+    }
+
+    /// Returns the FEN of the position the game started from.
+    fn start_fen(&self) -> String {
+        self.history.start_fen().to_string()
+    }
+
+    /// Writes the current position as FEN to `POSITION_FILE` and returns it.
+    pub fn save_position(&self) -> io::Result<String> {
+        let fen = self.current_fen();
+        std::fs::write(POSITION_FILE, format!("{}\n", fen))?;
+        Ok(fen)
+    }
+
+    fn restore_position(&mut self, position: Game) {
+        self.gameboard = position;
+        self.draw_offer = None;
+        self.dragging = None;
+        self.cancel_engine_search();
+        self.ongoing_promotion = None;
+        self.hovered_promotion_square = None;
+        self.pending_move = None;
+        self.selected_cell = None;
+        self.selected_cell_moves.drain();
+        self.animation.cancel_animations();
+        self.update_game_state();
+        self.scroll_to_current();
+
+        // Navigating the history restores the times left at that point and
+        // hands the move to the side to play in the restored position.
+        let now = self.time.now();
+        let ply = self.history.current();
+        if let Some(clock) = self.clock.as_mut() {
+            let times = match ply {
+                0 => Some([clock.control.initial; 2]),
+                _ => self.history.moves()[ply - 1].clock,
+            };
+            if let Some(times) = times {
+                clock.set_times(times, now);
+            }
+            if clock.is_running() {
+                clock.start(now, self.gameboard.current_turn());
+            }
+        }
+    }
+
+    /// Writes the moves played so far to a PGN file in the working directory
+    /// and returns its path.
+    pub fn save_game(&mut self) -> io::Result<String> {
+        let path = format!("{}{}.pgn", SAVED_GAME_PREFIX, current_time());
+        let result = self.result.map_or("*", |result| result.pgn_token());
+        save_pgn(&path, &self.history, result)?;
+        self.pgn_path = Some(path.clone());
+        Ok(path)
+    }
+
+    fn record_move(&mut self, from: BoardPos, to: BoardPos, promotion: Option<PieceType>) {
+        self.cancel_engine_search();
+
+        // Moving instead of answering a draw offer declines it.
+        let mover = opponent(self.gameboard.current_turn());
+        if self.draw_offer.is_some() && self.draw_offer != Some(mover) {
+            self.draw_offer = None;
+        }
+        let now = self.time.now();
+        if let Some(clock) = self.clock.as_mut() {
+            if clock.is_running() {
+                clock.switch(now);
+            }
+            else {
+                clock.start(now, self.gameboard.current_turn());
+            }
+        }
+
+        let before = self.history.current_position();
+        let san = to_san(&before, &self.gameboard, &from, &to, promotion);
+        let captured = captured_piece(&before, &self.gameboard);
+        let clock = self.clock.as_ref().map(|clock| clock.times(now));
+        self.history.push(MoveRecord { from, to, promotion, san, captured, clock }, self.gameboard.clone());
+        self.update_game_state();
+        self.scroll_to_current();
+
+        if let Some(result) = outcome::detect(&self.gameboard, &self.history) {
+            self.finish_game(result);
+        }
+    }
+
+    fn update_game_state(&mut self) {
+        self.is_check = false;
+        self.is_checkmate = false;
+
+        match self.gameboard.get_state() {
+            GameState::Check(_) => self.is_check = true,
+            GameState::Checkmate(_) => self.is_checkmate = true,
+            _ => {}
+        }
+
+        if self.auto_flip {
+            self.flipped = self.gameboard.current_turn() == board::Color::Black;
+        }
+        if self.move_input.is_some() {
+            self.update_move_suggestions();
+        }
     }
 
     fn update_selected_cell_moves(&mut self) {
@@ -152,32 +1388,46 @@ impl GameboardController {
         }
     }
 
-    fn check_selected_move(&mut self, from: BoardPos, to: BoardPos) -> bool {
-        if !self.animation.running && self.selected_cell_moves.contains(&to) {
-            match self.gameboard.move_piece(&from, &to) {
-                Ok(_) => {
-                    println!("Moved");
-                    self.animation.set_animation(current_time(), 150, &from, &to);
-                    self.is_check = false;
-                    self.is_checkmate = false;
-                },
-                Err(MovePieceError::NoTile) => panic!("The tile is empty!"),
-                Err(MovePieceError::NotCurrentTurn) => panic!("You cannot move your opponent's pieces!"),
-                Err(MovePieceError::InvalidMove) => panic!("That is not a valid move."),
-            }
+    /// Moves the piece on `from` to `to` for the side to move.
+    pub fn try_move(&mut self, from: BoardPos, to: BoardPos) -> Result<CommandOutcome, CommandError> {
+        if self.result.is_some() {
+            return Err(CommandError::GameOver);
+        }
+        if self.ongoing_promotion.is_some() {
+            return Err(CommandError::PromotionPending);
+        }
+        self.animation.update();
+        if self.animation.is_running() {
+            return Err(CommandError::Animating);
+        }
+        if self.is_engine_turn() {
+            return Err(CommandError::EngineTurn);
+        }
 
-            match self.gameboard.get_state() {
-                GameState::Normal => println!("Game is in progress"),
-                GameState::Check(_) => self.is_check = true,
-                GameState::Checkmate(_) => self.is_checkmate = true,
-                GameState::PromotionRequired(_) => {
-                    println!("Select promotion!");
-                    self.ongoing_promotion = Some(to);
-                    //self.gameboard.promote(PieceType::Queen);
-                },
-            };
-            return true;
+        let legal = self.gameboard.get_legal_moves(&from)
+            .is_ok_and(|moves| moves.into_iter().any(|mv| mv == to));
+        let before = self.gameboard.clone();
+        if !legal || self.gameboard.move_piece(&from, &to).is_err() {
+            return Err(CommandError::IllegalMove);
+        }
+        self.animation.animate_move(&before, &self.gameboard);
+        self.selected_cell = None;
+        self.selected_cell_moves.drain();
+
+        if let GameState::PromotionRequired(_) = self.gameboard.get_state() {
+            self.ongoing_promotion = Some(to.clone());
+            self.pending_move = Some((from, to));
+            return Ok(CommandOutcome::PromotionPending);
+        }
+        self.record_move(from, to, None);
+        Ok(self.moved())
+    }
+
+    /// Reports the move just recorded.
+    fn moved(&self) -> CommandOutcome {
+        CommandOutcome::Moved {
+            san: self.history.last_move().map_or(String::new(), |record| record.san.clone()),
+            result: self.result,
         }
-        return false;
     }
 }
\ No newline at end of file
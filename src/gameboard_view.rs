@@ -3,13 +3,24 @@ use graphics::{Context, Graphics};
 use graphics::character::CharacterCache;
 use alvinw_chess::{pos::BoardPos, piece::PieceType, board};
 
-use crate::gameboard_controller::GameboardController;
-use crate::utils::current_time;
+use crate::chess_clock::format_time;
+use crate::gameboard_controller::{button_rect, dialog_row_rect, GameboardController};
+use crate::new_game::DIALOG_ROWS;
+use crate::piece_set::{PieceImage, PieceSet};
+use crate::rules::{opponent, PROMOTION_PIECES};
+use crate::theme::Theme;
+use crate::uci::Score;
+use crate::utils::Clock;
+
+/// Window size the default settings are laid out for.
+const DESIGN_WINDOW_SIZE: [f64; 2] = [900.0, 600.0];
 
 /// Stores gameboard view settings.
 pub struct GameboardViewSettings {
     pub position: [f64; 2],
     pub size: f64,
+    /// Size of the piece font, scaled with the board.
+    pub piece_font_size: u32,
     pub border_color: Color,
     pub board_edge_radius: f64,
     pub cell_edge_radius: f64,
@@ -18,15 +29,59 @@ pub struct GameboardViewSettings {
     pub hovered_promotion_cell_color: Color,
     pub dark_square_color: Color,
     pub light_square_color: Color,
-    pub text_color: Color
+    pub text_color: Color,
+    pub clock_font_size: u32,
+    pub clock_color: Color,
+    pub clock_low_time_color: Color,
+    pub active_clock_background_color: Color,
+    pub info_font_size: u32,
+    pub info_color: Color,
+    pub last_move_from_color: Color,
+    pub last_move_to_color: Color,
+    pub annotation_color: Color,
+    pub annotation_radius: f64,
+    pub keyboard_cursor_color: Color,
+    pub keyboard_cursor_radius: f64,
+    pub coordinate_font_size: u32,
+    pub coordinate_color: Color,
+    /// Draws the coordinates in the corner squares instead of outside the border.
+    pub coordinates_inside: bool,
+    /// Move list area as `[x, y, width, height]`.
+    pub move_list_rect: [f64; 4],
+    pub move_list_font_size: u32,
+    pub move_list_row_height: f64,
+    pub move_list_background_color: Color,
+    pub move_list_text_color: Color,
+    pub move_list_current_move_color: Color,
+    pub captured_font_size: u32,
+    pub captured_piece_color: Color,
+    pub result_background_color: Color,
+    pub result_text_color: Color,
+    pub result_font_size: u32,
+    pub button_position: [f64; 2],
+    pub button_size: [f64; 2],
+    pub button_font_size: u32,
+    pub button_color: Color,
+    pub button_text_color: Color,
+    pub dialog_background_color: Color,
+    pub dialog_row_color: Color,
+    pub dialog_focused_row_color: Color,
+    pub dialog_font_size: u32,
+    /// Move input line as `[x, y, width, height]`.
+    pub move_input_rect: [f64; 4],
+    pub move_input_font_size: u32,
+    pub move_input_background_color: Color,
+    pub move_input_focused_color: Color,
+    pub move_input_error_color: Color
 }
 
 impl GameboardViewSettings {
     /// Creates new gameboard view settings.
     pub fn new() -> GameboardViewSettings {
         GameboardViewSettings {
-            position: [250.0, 100.0],
+            position: [60.0, 100.0],
             size: 400.0,
+            piece_font_size: 34,
             border_color: [0.0, 0.0, 0.0, 1.0],
             board_edge_radius: 3.0,
             cell_edge_radius: 1.0,
@@ -36,8 +91,120 @@ impl GameboardViewSettings {
             dark_square_color: [0.4, 0.3, 0.3, 1.0],
             light_square_color: [0.6, 0.45, 0.45, 1.0],
             text_color: [0.0, 0.0, 0.1, 1.0],
+            clock_font_size: 28,
+            clock_color: [0.9, 0.9, 0.9, 1.0],
+            clock_low_time_color: [1.0, 0.3, 0.3, 1.0],
+            active_clock_background_color: [0.2, 0.2, 0.35, 1.0],
+            info_font_size: 14,
+            info_color: [0.85, 0.85, 0.85, 1.0],
+            last_move_from_color: [0.55, 0.55, 0.35, 1.0],
+            last_move_to_color: [0.65, 0.65, 0.35, 1.0],
+            annotation_color: [0.1, 0.6, 0.2, 0.7],
+            annotation_radius: 4.0,
+            keyboard_cursor_color: [0.95, 0.8, 0.2, 1.0],
+            keyboard_cursor_radius: 2.0,
+            coordinate_font_size: 12,
+            coordinate_color: [0.85, 0.85, 0.85, 1.0],
+            coordinates_inside: false,
+            move_list_rect: [480.0, 150.0, 200.0, 290.0],
+            move_list_font_size: 14,
+            move_list_row_height: 20.0,
+            move_list_background_color: [0.2, 0.2, 0.35, 1.0],
+            move_list_text_color: [0.9, 0.9, 0.9, 1.0],
+            move_list_current_move_color: [0.45, 0.45, 0.65, 1.0],
+            captured_font_size: 20,
+            captured_piece_color: [0.05, 0.05, 0.1, 1.0],
+            result_background_color: [0.1, 0.1, 0.15, 0.75],
+            result_text_color: [1.0, 1.0, 1.0, 1.0],
+            result_font_size: 32,
+            button_position: [710.0, 150.0],
+            button_size: [130.0, 30.0],
+            button_font_size: 14,
+            button_color: [0.45, 0.45, 0.65, 1.0],
+            button_text_color: [1.0, 1.0, 1.0, 1.0],
+            dialog_background_color: [0.1, 0.1, 0.15, 0.9],
+            dialog_row_color: [0.3, 0.3, 0.45, 1.0],
+            dialog_focused_row_color: [0.5, 0.5, 0.75, 1.0],
+            dialog_font_size: 16,
+            move_input_rect: [480.0, 510.0, 200.0, 26.0],
+            move_input_font_size: 14,
+            move_input_background_color: [0.2, 0.2, 0.35, 1.0],
+            move_input_focused_color: [0.35, 0.35, 0.55, 1.0],
+            move_input_error_color: [1.0, 0.5, 0.5, 1.0],
         }
     }
+
+    /// Recomputes the layout for a window of `window_size`.
+    ///
+    /// The default layout is scaled to fit, so the side panels keep their
+    /// room, and centred in the window.
+    pub fn layout(&mut self, window_size: [f64; 2]) {
+        let defaults = GameboardViewSettings::new();
+        let scale = (window_size[0] / DESIGN_WINDOW_SIZE[0]).min(window_size[1] / DESIGN_WINDOW_SIZE[1]);
+        let font = |size: u32| ((size as f64 * scale).round() as u32).max(1);
+
+        self.size = defaults.size * scale;
+        self.position = [
+            (window_size[0] - DESIGN_WINDOW_SIZE[0] * scale) / 2.0 + defaults.position[0] * scale,
+            (window_size[1] - DESIGN_WINDOW_SIZE[1] * scale) / 2.0 + defaults.position[1] * scale,
+        ];
+
+        // Panels keep their place relative to the board.
+        let origin = defaults.position;
+        let position = self.position;
+        let place = |rect: [f64; 4]| [
+            position[0] + (rect[0] - origin[0]) * scale,
+            position[1] + (rect[1] - origin[1]) * scale,
+            rect[2] * scale,
+            rect[3] * scale,
+        ];
+        self.move_list_rect = place(defaults.move_list_rect);
+        self.move_list_row_height = defaults.move_list_row_height * scale;
+        self.move_input_rect = place(defaults.move_input_rect);
+        let button = place([defaults.button_position[0], defaults.button_position[1], defaults.button_size[0], defaults.button_size[1]]);
+        self.button_position = [button[0], button[1]];
+        self.button_size = [button[2], button[3]];
+
+        self.board_edge_radius = defaults.board_edge_radius * scale;
+        self.cell_edge_radius = defaults.cell_edge_radius * scale;
+        self.annotation_radius = defaults.annotation_radius * scale;
+        self.keyboard_cursor_radius = defaults.keyboard_cursor_radius * scale;
+
+        self.piece_font_size = font(defaults.piece_font_size);
+        self.clock_font_size = font(defaults.clock_font_size);
+        self.info_font_size = font(defaults.info_font_size);
+        self.coordinate_font_size = font(defaults.coordinate_font_size);
+        self.move_list_font_size = font(defaults.move_list_font_size);
+        self.captured_font_size = font(defaults.captured_font_size);
+        self.result_font_size = font(defaults.result_font_size);
+        self.button_font_size = font(defaults.button_font_size);
+        self.dialog_font_size = font(defaults.dialog_font_size);
+        self.move_input_font_size = font(defaults.move_input_font_size);
+    }
+
+    /// Takes the colours of the board and panels from `theme`.
+    pub fn apply_theme(&mut self, theme: &Theme) {
+        self.light_square_color = theme.light_square;
+        self.dark_square_color = theme.dark_square;
+        self.border_color = theme.border;
+        self.text_color = theme.pieces;
+        self.last_move_from_color = theme.last_move_from;
+        self.last_move_to_color = theme.last_move_to;
+        self.annotation_color = theme.annotation;
+        self.keyboard_cursor_color = theme.keyboard_cursor;
+        self.coordinate_color = theme.coordinates;
+
+        self.move_list_background_color = theme.panel;
+        self.move_input_background_color = theme.panel;
+        self.active_clock_background_color = theme.panel;
+        self.move_list_text_color = theme.panel_text;
+        self.clock_color = theme.panel_text;
+        self.info_color = theme.panel_text;
+        self.move_list_current_move_color = theme.highlight;
+        self.move_input_focused_color = theme.highlight;
+        self.button_color = theme.highlight;
+        self.dialog_focused_row_color = theme.highlight;
+    }
 }
 
 /// Stores visual information about a gameboard.
@@ -55,16 +222,21 @@ impl GameboardView {
     }
 
     /// Draw gameboard.
+    ///
+    /// `pieces` is the piece artwork, `glyphs` holds the chess piece font used
+    /// by the font piece set and `text_glyphs` the font for text.
     pub fn draw<G: Graphics, C>(
         &self,
         controller: &mut GameboardController,
+        pieces: &PieceSet<G::Texture>,
         glyphs: &mut C,
+        text_glyphs: &mut C,
         c: &Context,
         g: &mut G
     ) 
     where C: CharacterCache<Texture = G::Texture>
     {
-        use graphics::{Image, Line, Rectangle, Transformed};
+        use graphics::{Ellipse, Line, Rectangle};
 
         let ref settings = self.settings;
         
@@ -89,7 +261,8 @@ impl GameboardView {
         let cell_size = settings.size / 8.0;
         for rank in 0..8 {  
             for file in 0..8 {
-                let pos = [file as f64 * cell_size, (7 - rank) as f64 * cell_size];
+                let screen = controller.to_screen(file as f64, rank as f64);
+                let pos = [screen[0] * cell_size, screen[1] * cell_size];
                 let cell_rect = [
                     settings.position[0] + pos[0] + 1.0, settings.position[1] + pos[1] + 1.0,
                     cell_size - 2.0, cell_size - 2.0
@@ -98,6 +271,14 @@ impl GameboardView {
                     false => settings.light_square_color,
                     true => settings.dark_square_color,
                 };
+                if let Some(last_move) = controller.history.last_move() {
+                    if last_move.from == BoardPos::new(file, rank) {
+                        square_color = settings.last_move_from_color;
+                    }
+                    else if last_move.to == BoardPos::new(file, rank) {
+                        square_color = settings.last_move_to_color;
+                    }
+                }
                 if let Some(selected_cell) = controller.selected_cell.clone() {
                     if selected_cell == BoardPos::new(file, rank) {
                         square_color[1] += 0.2;
@@ -125,76 +306,91 @@ impl GameboardView {
             }
         }
         
-        let text_image = Image::new_color(settings.text_color);
+        controller.animation.update();
+
+        // Captured pieces fade out beneath the pieces arriving on their square.
+        for (square, piece, color, opacity) in controller.animation.fading_pieces() {
+            let screen = controller.to_screen(square.file() as f64, square.rank() as f64);
+            let piece_rect = [
+                settings.position[0] + cell_size * screen[0],
+                settings.position[1] + cell_size * screen[1],
+                cell_size, cell_size
+            ];
+            let mut faded_color = settings.text_color;
+            faded_color[3] *= opacity;
+            self.draw_piece(pieces, glyphs, piece, color, faded_color, piece_rect, c, g);
+        }
+
         for rank in 0..8 {
             for file in 0..8 {
                 
                 let pos = BoardPos::new(file, rank);
+                if controller.dragging == Some(pos.clone()) {
+                    continue;
+                }
                 if let Some(tile) = controller.gameboard.get_tile(&pos) {
-                    let ch = match tile.piece() {
-                        PieceType::Pawn => match tile.color() {
-                            board::Color::White => 'p',
-                            board::Color::Black => 'o',
-                        },
-                        PieceType::Knight => match tile.color() {
-                            board::Color::White => 'h',
-                            board::Color::Black => 'j',
-                            
-                        },
-                        PieceType::Bishop => match tile.color() {
-                            board::Color::White => 'b',
-                            board::Color::Black => 'n',
-                        },
-                        PieceType::Rook => match tile.color() {
-                            board::Color::White => 'r',
-                            board::Color::Black => 't',
-                        },
-                        PieceType::Queen => match tile.color() {
-                            board::Color::White => 'q',
-                            board::Color::Black => 'w',
-                        },
-                        PieceType::King => match tile.color() {
-                            board::Color::White => 'k',
-                            board::Color::Black => 'l',
-                        },
-                    };
-                    if let Ok(character) = glyphs.character(34, ch) {
-
-                        let mut piece_file = file as f64;
-                        let mut piece_rank = rank as f64;
-
-                        if controller.animation.running {
-                            if let Some(target) = controller.animation.target() {
-                                if target == pos {
-                                    if let Some(position) = controller.animation.update_animation(current_time()) {
-                                        piece_file = position.0;
-                                        piece_rank = position.1;
-                                    }
-                                }
-                            }
-                        }
+                    let (piece_file, piece_rank) = controller.animation.position(&pos)
+                        .unwrap_or((file as f64, rank as f64));
 
-                        let pos = [
-                            settings.position[0] + cell_size * (piece_file + 0.05),
-                            settings.position[1] + cell_size * (7.0 - piece_rank + 0.85), 
-                        ];
-                        let ch_x = pos[0] + character.left();
-                        let ch_y = pos[1] - character.top();
-                        let text_image = text_image.src_rect([
-                            character.atlas_offset[0],
-                            character.atlas_offset[1],
-                            character.atlas_size[0],
-                            character.atlas_size[1],
-                        ]);
-                        text_image.draw(character.texture,
-                                        &c.draw_state,
-                                        c.transform.trans(ch_x, ch_y),
-                                        g);
-                    }
+                    let screen = controller.to_screen(piece_file, piece_rank);
+                    let piece_rect = [
+                        settings.position[0] + cell_size * screen[0],
+                        settings.position[1] + cell_size * screen[1],
+                        cell_size, cell_size
+                    ];
+                    self.draw_piece(pieces, glyphs, tile.piece(), tile.color(), settings.text_color, piece_rect, c, g);
                 }
             }
         }
 
+        // Draw circles and arrows from the right mouse button.
+        let cell_centre = |pos: &BoardPos| {
+            let screen = controller.to_screen(pos.file() as f64, pos.rank() as f64);
+            [
+                settings.position[0] + (screen[0] + 0.5) * cell_size,
+                settings.position[1] + (screen[1] + 0.5) * cell_size,
+            ]
+        };
+        let circle = Ellipse::new_border(settings.annotation_color, settings.annotation_radius / 2.0);
+        for square in &controller.circles {
+            let centre = cell_centre(square);
+            let radius = cell_size / 2.0 - settings.annotation_radius;
+            circle.draw([centre[0] - radius, centre[1] - radius, radius * 2.0, radius * 2.0],
+                        &c.draw_state, c.transform, g);
+        }
+        let arrow = Line::new_round(settings.annotation_color, settings.annotation_radius);
+        for (from, to) in &controller.arrows {
+            let start = cell_centre(from);
+            let end = cell_centre(to);
+            arrow.draw_arrow([start[0], start[1], end[0], end[1]], cell_size * 0.3,
+                             &c.draw_state, c.transform, g);
+        }
+
+        // Draw the keyboard cursor as a frame inside its square.
+        if let Some(cursor) = controller.keyboard_cursor.clone() {
+            let screen = controller.to_screen(cursor.file() as f64, cursor.rank() as f64);
+            let inset = settings.keyboard_cursor_radius * 2.0;
+            let cursor_rect = [
+                settings.position[0] + screen[0] * cell_size + inset,
+                settings.position[1] + screen[1] * cell_size + inset,
+                cell_size - inset * 2.0,
+                cell_size - inset * 2.0,
+            ];
+            Rectangle::new_border(settings.keyboard_cursor_color, settings.keyboard_cursor_radius)
+                .draw(cursor_rect, &c.draw_state, c.transform, g);
+        }
+
+        // Draw the dragged piece centred on the cursor, above the other pieces.
+        if let Some(dragged_cell) = controller.dragging.clone() {
+            if let Some(tile) = controller.gameboard.get_tile(&dragged_cell) {
+                let cursor_pos = controller.cursor_pos();
+                let piece_rect = [cursor_pos[0] - cell_size / 2.0, cursor_pos[1] - cell_size / 2.0, cell_size, cell_size];
+                self.draw_piece(pieces, glyphs, tile.piece(), tile.color(), settings.text_color, piece_rect, c, g);
+            }
+        }
+
+        self.draw_captured_pieces(controller, pieces, glyphs, text_glyphs, c, g);
+
         let board_rect = [
             settings.position[0],
             settings.position[1],
@@ -210,20 +406,11 @@ impl GameboardView {
         .draw(board_rect, &c.draw_state, c.transform, g);
 
         
-        if let Some(promotion_pos) = controller.ongoing_promotion.clone() {
-            let file = promotion_pos.file() as f64 - 1.5;
-            let mut rank = (7 - promotion_pos.rank()) as f64;
-            let piece_textures: [char; 4];
-
+        if let Some(menu_origin) = controller.promotion_menu_origin() {
+            let file = menu_origin[0];
+            let rank = menu_origin[1];
             //Opposite color because api swaps turn before promotion
-            if controller.gameboard.current_turn() != board::Color::White {
-                piece_textures = ['h', 'b', 'r', 'q'];
-                rank -= 1.0;
-            }
-            else {
-                piece_textures = ['j', 'n', 't', 'w'];
-                rank += 1.0;
-            }
+            let color = opponent(controller.gameboard.current_turn());
 
             for file_offset in 0..4 {
                 let pos = [(file + file_offset as f64) * cell_size, rank * cell_size];
@@ -241,21 +428,455 @@ impl GameboardView {
                 Rectangle::new(square_color)
                     .draw(cell_rect, &c.draw_state, c.transform, g);
 
-                if let Ok(character) = glyphs.character(34, piece_textures[file_offset]) {
-                    let ch_x = settings.position[0] + pos[0] + character.left() + cell_size * 0.05;
-                    let ch_y = settings.position[1] + pos[1] - character.top() + cell_size * 0.85;
-                    let text_image = text_image.src_rect([
-                        character.atlas_offset[0],
-                        character.atlas_offset[1],
-                        character.atlas_size[0],
-                        character.atlas_size[1],
-                    ]);
-                    text_image.draw(character.texture,
-                                    &c.draw_state,
-                                    c.transform.trans(ch_x, ch_y),
-                                    g);
+                let piece_rect = [settings.position[0] + pos[0], settings.position[1] + pos[1], cell_size, cell_size];
+                self.draw_piece(pieces, glyphs, PROMOTION_PIECES[file_offset], color, settings.text_color, piece_rect, c, g);
+            }
+        }
+
+        if controller.show_coordinates {
+            self.draw_coordinates(controller, text_glyphs, c, g);
+        }
+        self.draw_clocks(controller, text_glyphs, c, g);
+        self.draw_move_list(controller, text_glyphs, c, g);
+        self.draw_move_input(controller, text_glyphs, c, g);
+        self.draw_engine_info(controller, text_glyphs, c, g);
+        self.draw_buttons(controller, text_glyphs, c, g);
+
+        if controller.new_game_dialog.is_some() {
+            self.draw_new_game_dialog(controller, text_glyphs, c, g);
+        }
+        else if controller.show_result() {
+            self.draw_result(controller, text_glyphs, c, g);
+        }
+    }
+
+    /// Draws the new game dialog over the board.
+    fn draw_new_game_dialog<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        text_glyphs: &mut C,
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Rectangle, Text, Transformed};
+
+        let ref settings = self.settings;
+        let dialog = match controller.new_game_dialog.as_ref() {
+            Some(dialog) => dialog,
+            None => return,
+        };
+
+        let board_rect = [settings.position[0], settings.position[1], settings.size, settings.size];
+        Rectangle::new(settings.dialog_background_color).draw(board_rect, &c.draw_state, c.transform, g);
+
+        let text = Text::new_color(settings.result_text_color, settings.dialog_font_size);
+        let font_size = settings.dialog_font_size as f64;
+        let title = if dialog.discards_game { "Abandon the current game?" } else { "New game" };
+        let first_row = dialog_row_rect(settings.position, settings.size, 0);
+        text.draw(title, text_glyphs, &c.draw_state,
+                  c.transform.trans(first_row[0], first_row[1] - font_size), g)
+            .ok();
+
+        for row in 0..DIALOG_ROWS {
+            let rect = dialog_row_rect(settings.position, settings.size, row);
+            let color = if row == dialog.focused_row { settings.dialog_focused_row_color } else { settings.dialog_row_color };
+            Rectangle::new(color).draw(rect, &c.draw_state, c.transform, g);
+            text.draw(&dialog.row_label(row), text_glyphs, &c.draw_state,
+                      c.transform.trans(rect[0] + 8.0, rect[1] + (rect[3] + font_size) / 2.0 - 2.0), g)
+                .ok();
+        }
+    }
+
+    /// Draws the resign and draw offer buttons, and who offered a draw.
+    fn draw_buttons<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        text_glyphs: &mut C,
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Rectangle, Text, Transformed};
+
+        let ref settings = self.settings;
+        let text = Text::new_color(settings.button_text_color, settings.button_font_size);
+        let font_size = settings.button_font_size as f64;
+        let actions = controller.available_actions();
+
+        for (index, action) in actions.iter().enumerate() {
+            let rect = button_rect(settings.button_position, settings.button_size, index);
+            Rectangle::new(settings.button_color).draw(rect, &c.draw_state, c.transform, g);
+            text.draw(action.label(), text_glyphs, &c.draw_state,
+                      c.transform.trans(rect[0] + 8.0, rect[1] + (rect[3] + font_size) / 2.0 - 2.0), g)
+                .ok();
+        }
+
+        if let Some(color) = controller.draw_offer {
+            let offer = match color {
+                board::Color::White => "White offers a draw",
+                board::Color::Black => "Black offers a draw",
+            };
+            let rect = button_rect(settings.button_position, settings.button_size, actions.len());
+            text.draw(offer, text_glyphs, &c.draw_state,
+                      c.transform.trans(rect[0], rect[1] + font_size), g)
+                .ok();
+        }
+    }
+
+    /// Covers the board with the winner and the reason the game ended.
+    fn draw_result<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        text_glyphs: &mut C,
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Rectangle, Text, Transformed};
+
+        let ref settings = self.settings;
+        let result = match controller.result {
+            Some(result) => result,
+            None => return,
+        };
+
+        let board_rect = [settings.position[0], settings.position[1], settings.size, settings.size];
+        Rectangle::new(settings.result_background_color).draw(board_rect, &c.draw_state, c.transform, g);
+
+        let centre_x = settings.position[0] + settings.size / 2.0;
+        let centre_y = settings.position[1] + settings.size / 2.0;
+        let saved = match &controller.saved_game {
+            Some(Ok(path)) => format!("Saved to {}", path),
+            Some(Err(err)) => format!("Could not save game: {}", err),
+            None => String::new(),
+        };
+        let lines = [
+            (result.headline(), settings.result_font_size, centre_y),
+            (result.reason(), settings.result_font_size / 2, centre_y + settings.result_font_size as f64),
+            ("Press R for a new game", settings.result_font_size / 2, centre_y + settings.result_font_size as f64 * 2.0),
+            (saved.as_str(), settings.result_font_size / 3, centre_y + settings.result_font_size as f64 * 3.0),
+        ];
+        for (line, font_size, y) in lines {
+            let width = text_glyphs.width(font_size, line).unwrap_or(0.0);
+            Text::new_color(settings.result_text_color, font_size)
+                .draw(line, text_glyphs, &c.draw_state, c.transform.trans(centre_x - width / 2.0, y), g)
+                .ok();
+        }
+    }
+
+    /// Draws a piece filling the square `rect`. Font glyphs are drawn in
+    /// `glyph_color`, images take only its opacity.
+    fn draw_piece<G: Graphics, C>(
+        &self,
+        pieces: &PieceSet<G::Texture>,
+        glyphs: &mut C,
+        piece: PieceType,
+        color: board::Color,
+        glyph_color: Color,
+        rect: [f64; 4],
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Image, Transformed};
+
+        match pieces.image(piece, color) {
+            PieceImage::Glyph(ch) => {
+                let cell_size = self.settings.size / 8.0;
+                let font_size = (self.settings.piece_font_size as f64 * rect[3] / cell_size).round() as u32;
+                if let Ok(character) = glyphs.character(font_size, ch) {
+                    let ch_x = rect[0] + rect[2] * 0.05 + character.left();
+                    let ch_y = rect[1] + rect[3] * 0.85 - character.top();
+                    Image::new_color(glyph_color)
+                        .src_rect([
+                            character.atlas_offset[0],
+                            character.atlas_offset[1],
+                            character.atlas_size[0],
+                            character.atlas_size[1],
+                        ])
+                        .draw(character.texture, &c.draw_state, c.transform.trans(ch_x, ch_y), g);
                 }
+            },
+            PieceImage::Sprite(texture, source) => {
+                Image::new_color([1.0, 1.0, 1.0, glyph_color[3]])
+                    .rect(rect)
+                    .src_rect(source)
+                    .draw(texture, &c.draw_state, c.transform, g);
+            },
+        }
+    }
+
+    /// Draws the pieces each player has captured next to that player's side of
+    /// the board, followed by their material advantage if they are ahead.
+    fn draw_captured_pieces<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        pieces: &PieceSet<G::Texture>,
+        glyphs: &mut C,
+        text_glyphs: &mut C,
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Text, Transformed};
+
+        let ref settings = self.settings;
+        let font_size = settings.captured_font_size as f64;
+        let captured = controller.captured_pieces();
+        let balance = controller.material_balance();
+
+        let top_y = settings.position[1] - font_size * 0.3;
+        let bottom_y = settings.position[1] + settings.size + font_size * 1.9;
+        // White's captures are Black pieces and are shown on White's side.
+        let rows = [
+            (board::Color::White, &captured[0], board::Color::Black, balance),
+            (board::Color::Black, &captured[1], board::Color::White, -balance),
+        ];
+
+        for (player, captured_pieces, piece_color, advantage) in rows {
+            let at_bottom = (player == board::Color::White) != controller.flipped;
+            let y = if at_bottom { bottom_y } else { top_y };
+            let mut x = settings.position[0];
+
+            // Pieces are drawn in squares whose glyphs match the font size.
+            let square = font_size * settings.size / 8.0 / settings.piece_font_size as f64;
+            for piece in captured_pieces {
+                let piece_rect = [x - square * 0.05, y - square * 0.85, square, square];
+                self.draw_piece(pieces, glyphs, *piece, piece_color, settings.captured_piece_color, piece_rect, c, g);
+                x += font_size * 0.6;
+            }
+
+            if advantage > 0 {
+                Text::new_color(settings.captured_piece_color, settings.captured_font_size * 2 / 3)
+                    .draw(&format!("+{}", advantage), text_glyphs, &c.draw_state,
+                          c.transform.trans(x + font_size * 0.4, y - font_size * 0.1), g)
+                    .ok();
+            }
+        }
+    }
+
+    /// Draws the file letters along the bottom edge and the rank numbers along
+    /// the left edge of the board as it is currently oriented.
+    fn draw_coordinates<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        text_glyphs: &mut C,
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Text, Transformed};
+
+        let ref settings = self.settings;
+        let cell_size = settings.size / 8.0;
+        let font_size = settings.coordinate_font_size as f64;
+        let text = Text::new_color(settings.coordinate_color, settings.coordinate_font_size);
+
+        for i in 0..8 {
+            let screen = controller.to_screen(i as f64, i as f64);
+            let file_label = ((b'a' + i) as char).to_string();
+            let rank_label = ((b'1' + i) as char).to_string();
+
+            let (file_x, file_y, rank_x, rank_y) = if settings.coordinates_inside {
+                (
+                    (screen[0] + 1.0) * cell_size - font_size * 0.8,
+                    settings.size - font_size * 0.3,
+                    font_size * 0.3,
+                    screen[1] * cell_size + font_size * 1.1,
+                )
+            }
+            else {
+                (
+                    (screen[0] + 0.5) * cell_size - font_size * 0.3,
+                    settings.size + font_size * 1.2,
+                    -font_size,
+                    (screen[1] + 0.5) * cell_size + font_size * 0.4,
+                )
+            };
+
+            text.draw(&file_label, text_glyphs, &c.draw_state,
+                      c.transform.trans(settings.position[0] + file_x, settings.position[1] + file_y), g)
+                .ok();
+            text.draw(&rank_label, text_glyphs, &c.draw_state,
+                      c.transform.trans(settings.position[0] + rank_x, settings.position[1] + rank_y), g)
+                .ok();
+        }
+    }
+
+    /// Draws the numbered move pairs of the game, highlighting the current move.
+    fn draw_move_list<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        text_glyphs: &mut C,
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Rectangle, Text, Transformed};
+
+        let ref settings = self.settings;
+        let rect = settings.move_list_rect;
+        let row_height = settings.move_list_row_height;
+        let rows = (rect[3] / row_height) as usize;
+        let column_width = rect[2] / 2.0;
+
+        Rectangle::new(settings.move_list_background_color).draw(rect, &c.draw_state, c.transform, g);
+
+        let moves = controller.history.moves();
+        let current = controller.history.current();
+        let text = Text::new_color(settings.move_list_text_color, settings.move_list_font_size);
+        let baseline = (row_height + settings.move_list_font_size as f64) / 2.0 - 2.0;
+
+        for row in 0..rows {
+            let index = controller.move_list_scroll + row;
+            if index * 2 >= moves.len() {
+                break;
+            }
+            let y = rect[1] + row as f64 * row_height;
+
+            if current > 0 && (current - 1) / 2 == index {
+                let x = if (current - 1) % 2 == 0 { rect[0] + column_width * 0.3 } else { rect[0] + column_width };
+                Rectangle::new(settings.move_list_current_move_color)
+                    .draw([x, y, column_width * 0.7, row_height], &c.draw_state, c.transform, g);
+            }
+
+            let number = format!("{}.", index + 1);
+            text.draw(&number, text_glyphs, &c.draw_state, c.transform.trans(rect[0] + 4.0, y + baseline), g).ok();
+            text.draw(&moves[index * 2].san, text_glyphs, &c.draw_state,
+                      c.transform.trans(rect[0] + column_width * 0.3 + 4.0, y + baseline), g).ok();
+            if let Some(record) = moves.get(index * 2 + 1) {
+                text.draw(&record.san, text_glyphs, &c.draw_state,
+                          c.transform.trans(rect[0] + column_width + 4.0, y + baseline), g).ok();
+            }
+        }
+    }
+
+    /// Draws the move input line with either the error for the last typed
+    /// move or the legal moves matching the typed text below it.
+    fn draw_move_input<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        text_glyphs: &mut C,
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Rectangle, Text, Transformed};
+
+        let ref settings = self.settings;
+        let rect = settings.move_input_rect;
+        let font_size = settings.move_input_font_size as f64;
+        let baseline = rect[1] + (rect[3] + font_size) / 2.0 - 2.0;
+        let text = Text::new_color(settings.move_list_text_color, settings.move_input_font_size);
+
+        let (background, line) = match controller.move_input.as_ref() {
+            Some(input) => (settings.move_input_focused_color, format!("{}_", input)),
+            None => (settings.move_input_background_color, String::from("Press / to type a move")),
+        };
+        Rectangle::new(background).draw(rect, &c.draw_state, c.transform, g);
+        text.draw(&line, text_glyphs, &c.draw_state, c.transform.trans(rect[0] + 4.0, baseline), g).ok();
+
+        let below = baseline + font_size * 1.6;
+        if let Some(error) = controller.move_input_error.as_ref() {
+            Text::new_color(settings.move_input_error_color, settings.move_input_font_size)
+                .draw(error, text_glyphs, &c.draw_state, c.transform.trans(rect[0], below), g)
+                .ok();
+        }
+        else if controller.move_input.is_some() {
+            let suggestions: Vec<&str> = controller.move_suggestions.iter().take(6).map(|name| name.as_str()).collect();
+            text.draw(&suggestions.join(" "), text_glyphs, &c.draw_state, c.transform.trans(rect[0], below), g).ok();
+        }
+    }
+
+    /// Draws the latest search information of the UCI engine below the board.
+    fn draw_engine_info<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        text_glyphs: &mut C,
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Text, Transformed};
+
+        let ref settings = self.settings;
+        let engine = match controller.uci_engine.as_ref() {
+            Some(engine) => engine,
+            None => return,
+        };
+
+        let mut line = engine.name.clone();
+        if let Some(info) = engine.info.as_ref() {
+            let score = match info.score {
+                Some(Score::Centipawns(cp)) => format!("{:+.2}", cp as f64 / 100.0),
+                Some(Score::Mate(moves)) => format!("#{}", moves),
+                None => String::new(),
+            };
+            let pv: Vec<&str> = info.pv.iter().take(8).map(|mv| mv.as_str()).collect();
+            line = format!("{}  depth {}  {}  {}", line, info.depth, score, pv.join(" "));
+        }
+
+        let y = settings.position[1] + settings.size + settings.captured_font_size as f64 * 2.0 + settings.info_font_size as f64 * 1.5;
+        Text::new_color(settings.info_color, settings.info_font_size)
+            .draw(&line, text_glyphs, &c.draw_state, c.transform.trans(settings.position[0], y), g)
+            .ok();
+    }
+
+    /// Draws both players' clocks to the right of the board, each on the side
+    /// of the board where that player's pieces start.
+    fn draw_clocks<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        text_glyphs: &mut C,
+        c: &Context,
+        g: &mut G
+    )
+    where C: CharacterCache<Texture = G::Texture>
+    {
+        use graphics::{Rectangle, Text, Transformed};
+
+        let ref settings = self.settings;
+        let clock = match controller.clock.as_ref() {
+            Some(clock) => clock,
+            None => return,
+        };
+        let now = controller.time.now();
+        let font_size = settings.clock_font_size as f64;
+        let x = settings.position[0] + settings.size * 1.05;
+
+        let (top, bottom) = if controller.flipped {
+            (board::Color::White, board::Color::Black)
+        }
+        else {
+            (board::Color::Black, board::Color::White)
+        };
+
+        for (color, y) in [(top, settings.position[1]), (bottom, settings.position[1] + settings.size - font_size * 1.5)] {
+            let is_active = clock.is_running() && controller.gameboard.current_turn() == color;
+            if is_active {
+                Rectangle::new(settings.active_clock_background_color)
+                    .draw([x - 5.0, y, font_size * 4.5, font_size * 1.5], &c.draw_state, c.transform, g);
+            }
+
+            let text_color = if clock.is_low(color, now) {
+                settings.clock_low_time_color
             }
+            else {
+                settings.clock_color
+            };
+            Text::new_color(text_color, settings.clock_font_size)
+                .draw(&format_time(clock.remaining(color, now)), text_glyphs, &c.draw_state, c.transform.trans(x, y + font_size * 1.1), g)
+                .ok();
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,113 @@
+use alvinw_chess::{game::Game, pos::BoardPos, piece::PieceType};
+
+use crate::fen::Fen;
+
+/// A single completed move, including the promotion choice if there was one.
+#[derive(Clone)]
+pub struct MoveRecord {
+    pub from: BoardPos,
+    pub to: BoardPos,
+    pub promotion: Option<PieceType>,
+    /// The move in Standard Algebraic Notation.
+    pub san: String,
+    /// The opponent piece taken by the move, including en passant captures.
+    pub captured: Option<PieceType>,
+    /// Time left for White and Black after the move, `None` in untimed games.
+    pub clock: Option<[u64; 2]>,
+}
+
+/// Stores every position of the game so moves can be undone and redone.
+pub struct GameHistory {
+    moves: Vec<MoveRecord>,
+    /// `positions[i]` is the position after the first `i` moves.
+    positions: Vec<Game>,
+    current: usize,
+    /// The start position with its castling rights and move counters.
+    start_fen: Fen,
+}
+
+impl GameHistory {
+    /// Creates a new history starting from the initial position.
+    pub fn new(start: Game) -> GameHistory {
+        GameHistory::from_fen(start, Fen::initial())
+    }
+
+    /// Creates a new history starting from `start`, which is described by `fen`.
+    pub fn from_fen(start: Game, fen: Fen) -> GameHistory {
+        GameHistory {
+            moves: Vec::new(),
+            positions: vec![start],
+            current: 0,
+            start_fen: fen,
+        }
+    }
+
+    /// Records a move and the resulting position, discarding any undone moves.
+    pub fn push(&mut self, record: MoveRecord, position: Game) {
+        self.moves.truncate(self.current);
+        self.positions.truncate(self.current + 1);
+        self.moves.push(record);
+        self.positions.push(position);
+        self.current += 1;
+    }
+
+    /// Steps one move back and returns the position to restore.
+    pub fn undo(&mut self) -> Option<Game> {
+        if self.current == 0 {
+            return None;
+        }
+        self.current -= 1;
+        Some(self.positions[self.current].clone())
+    }
+
+    /// Steps one move forward and returns the position to restore.
+    pub fn redo(&mut self) -> Option<Game> {
+        if self.current + 1 >= self.positions.len() {
+            return None;
+        }
+        self.current += 1;
+        Some(self.positions[self.current].clone())
+    }
+
+    /// Jumps to the position after `ply` moves and returns it.
+    pub fn go_to(&mut self, ply: usize) -> Option<Game> {
+        if ply >= self.positions.len() {
+            return None;
+        }
+        self.current = ply;
+        Some(self.positions[ply].clone())
+    }
+
+    /// Returns the position after `ply` moves.
+    pub fn position(&self, ply: usize) -> &Game {
+        &self.positions[ply]
+    }
+
+    /// Returns the FEN of the position the game started from.
+    pub fn start_fen(&self) -> &Fen {
+        &self.start_fen
+    }
+
+    /// Returns the position at the current point in the history.
+    pub fn current_position(&self) -> Game {
+        self.positions[self.current].clone()
+    }
+
+    /// Number of moves played up to the current point in the history.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// All recorded moves, including undone ones that can still be redone.
+    pub fn moves(&self) -> &[MoveRecord] {
+        &self.moves
+    }
+
+    /// The move that led to the current position.
+    pub fn last_move(&self) -> Option<&MoveRecord> {
+        if self.current == 0 {
+            return None;
+        }
+        self.moves.get(self.current - 1)
+    }
+}
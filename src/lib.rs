@@ -0,0 +1,24 @@
+//! Chess GUI built on piston. The controller can also be driven without a
+//! window through `GameboardController::select`, `try_move`,
+//! `choose_promotion` and `reset`.
+
+pub mod gameboard_controller;
+pub mod gameboard_view;
+pub mod animation;
+pub mod chess_clock;
+pub mod config;
+pub mod engine;
+pub mod fen;
+pub mod history;
+pub mod new_game;
+pub mod notation;
+pub mod outcome;
+pub mod pgn;
+pub mod piece_set;
+pub mod rules;
+pub mod theme;
+pub mod uci;
+pub mod utils;
+
+pub use crate::gameboard_controller::GameboardController;
+pub use crate::gameboard_view::{GameboardView, GameboardViewSettings};
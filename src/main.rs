@@ -1,52 +1,132 @@
-mod gameboard_controller;
-mod gameboard_view;
-mod animation;
-mod utils;
-
 use alvinw_chess::game::Game;
 use glutin_window::GlutinWindow;
 use opengl_graphics::{OpenGL, Filter, GlGraphics, GlyphCache, TextureSettings};
 use piston::event_loop::{EventSettings, Events};
-use piston::{EventLoop, RenderEvent, WindowSettings};
+use piston::{Button, EventLoop, Key, PressEvent, RenderEvent, Window, WindowSettings};
 
-pub use crate::gameboard_controller::GameboardController;
-pub use crate::gameboard_view::{GameboardView, GameboardViewSettings};
+use olindba_chess_gui::{GameboardController, GameboardView, GameboardViewSettings};
+use olindba_chess_gui::config::Config;
+use olindba_chess_gui::engine::STRENGTH_LEVELS;
+use olindba_chess_gui::fen::{read_fen, Fen};
+use olindba_chess_gui::piece_set::load_piece_sets;
 
 fn main() {
     let opengl = OpenGL::V3_2;
     let mut window: GlutinWindow = WindowSettings::new("Chess", [900, 600])
         .graphics_api(opengl)
-        .exit_on_esc(true)
+        .exit_on_esc(false)
         .build()
         .unwrap();
 
     let mut events = Events::new(EventSettings::new().lazy(false));
     let mut gl = GlGraphics::new(opengl);
 
+    let mut config = Config::load();
+    let themes = config.all_themes();
+
     let game= Game::new();
     let mut gameboard_controller = GameboardController::new(game);
+    gameboard_controller.show_coordinates = config.show_coordinates;
+    gameboard_controller.auto_flip = config.auto_flip;
+    gameboard_controller.engine_strength = config.engine_strength.min(STRENGTH_LEVELS.len() - 1);
+    gameboard_controller.animation.duration = config.animation_duration;
+    gameboard_controller.animation.easing = config.easing;
+    gameboard_controller.theme_names = themes.iter().map(|theme| theme.name.clone()).collect();
+    gameboard_controller.theme = themes.iter().position(|theme| theme.name == config.theme).unwrap_or(0);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--uci" {
+            if let Some(path) = args.next() {
+                gameboard_controller.connect_engine(&path);
+            }
+        }
+        else if arg == "--fen" {
+            if let Some(text) = args.next() {
+                match Fen::parse(&text).and_then(|fen| gameboard_controller.open_position(fen)) {
+                    Ok(()) => println!("Loaded position {}", text),
+                    Err(err) => println!("Could not load position {}: {}", text, err),
+                }
+            }
+        }
+        else if arg.ends_with(".fen") {
+            match read_fen(&arg).and_then(|fen| gameboard_controller.open_position(fen)) {
+                Ok(()) => println!("Loaded {}", arg),
+                Err(err) => println!("Could not load {}: {}", arg, err),
+            }
+        }
+        else {
+            match gameboard_controller.open_game(&arg) {
+                Ok(moves) => println!("Loaded {} ({} moves)", arg, moves),
+                Err(err) => println!("Could not load {}: {}", arg, err),
+            }
+        }
+    }
     let gameboard_view_settings = GameboardViewSettings::new();
-    let gameboard_view = GameboardView::new(gameboard_view_settings);
+    let mut gameboard_view = GameboardView::new(gameboard_view_settings);
+    let mut layout_size = [0.0; 2];
 
     let texture_settings = TextureSettings::new().filter(Filter::Nearest);
     let ref mut glyphs = GlyphCache::new("assets/CHEQ_TT.TTF", (), texture_settings)
         .expect("Could not load font");
+    let ref mut text_glyphs = GlyphCache::new("assets/DejaVuSansMono.ttf", (), TextureSettings::new())
+        .expect("Could not load font");
+    let piece_sets = load_piece_sets("assets/pieces");
+    gameboard_controller.piece_set_names = piece_sets.iter().map(|piece_set| piece_set.name.clone()).collect();
+    gameboard_controller.piece_set = piece_sets.iter().position(|piece_set| piece_set.name == config.piece_set).unwrap_or(0);
+    let mut applied_theme = None;
 
     while let Some(e) = events.next(&mut window) {
-        
+        // Esc closes the new game dialog when it is open and quits otherwise.
+        let dialog_open = gameboard_controller.new_game_dialog.is_some();
         gameboard_controller.event(
             gameboard_view.settings.position,
             gameboard_view.settings.size,
             &e,
         );
+        if let Some(Button::Keyboard(Key::Escape)) = e.press_args() {
+            if !dialog_open {
+                window.set_should_close(true);
+            }
+        }
+        gameboard_controller.button_event(
+            gameboard_view.settings.button_position,
+            gameboard_view.settings.button_size,
+            &e,
+        );
+        gameboard_controller.move_list_event(
+            gameboard_view.settings.move_list_rect,
+            gameboard_view.settings.move_list_row_height,
+            &e,
+        );
         
         if let Some(args) = e.render_args() {
+            if args.window_size != layout_size {
+                layout_size = args.window_size;
+                gameboard_view.settings.layout(layout_size);
+            }
+            if applied_theme != Some(gameboard_controller.theme) {
+                applied_theme = Some(gameboard_controller.theme);
+                gameboard_view.settings.apply_theme(&themes[gameboard_controller.theme]);
+            }
+            let background = themes[gameboard_controller.theme].background;
+
             gl.draw(args.viewport(), |c, g| {
                 use graphics::clear;
 
-                clear([0.3, 0.3, 0.5, 1.0], g);
-                gameboard_view.draw(&mut gameboard_controller, glyphs, &c, g);
+                clear(background, g);
+                gameboard_view.draw(&mut gameboard_controller, &piece_sets[gameboard_controller.piece_set], glyphs, text_glyphs, &c, g);
             });
         }
     }
+
+    config.theme = themes[gameboard_controller.theme].name.clone();
+    config.piece_set = piece_sets[gameboard_controller.piece_set].name.clone();
+    config.show_coordinates = gameboard_controller.show_coordinates;
+    config.auto_flip = gameboard_controller.auto_flip;
+    config.engine_strength = gameboard_controller.engine_strength;
+    config.animation_duration = gameboard_controller.animation.duration;
+    config.easing = gameboard_controller.animation.easing;
+    if let Err(err) = config.save() {
+        println!("Could not save settings: {}", err);
+    }
 }
@@ -0,0 +1,112 @@
+use alvinw_chess::board;
+
+use crate::chess_clock::TIME_CONTROLS;
+use crate::fen::POSITION_FILE;
+
+/// Where a new game starts from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum StartPosition {
+    Standard,
+    /// The position on the board when the dialog was opened.
+    CurrentPosition,
+    /// The position saved in `POSITION_FILE`.
+    SavedPosition,
+}
+
+/// Who plays against the user.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Opponent {
+    /// Both sides are played at this computer.
+    Human,
+    /// The connected UCI engine, or the built-in engine if there is none.
+    Computer,
+}
+
+/// Rows of the new game dialog, top to bottom.
+pub const DIALOG_ROWS: usize = 6;
+pub const START_ROW: usize = 4;
+pub const CANCEL_ROW: usize = 5;
+
+/// Choices made in the new game dialog.
+pub struct NewGameDialog {
+    pub start: StartPosition,
+    /// The side the user plays when playing the computer.
+    pub side: board::Color,
+    pub opponent: Opponent,
+    /// Index into `TIME_CONTROLS`.
+    pub time_control: usize,
+    /// Whether starting will throw away a game in progress.
+    pub discards_game: bool,
+    /// The highlighted row.
+    pub focused_row: usize,
+}
+
+impl NewGameDialog {
+    /// Creates a dialog preset with the current game's settings.
+    pub fn new(side: board::Color, opponent: Opponent, time_control: usize, discards_game: bool) -> NewGameDialog {
+        NewGameDialog {
+            start: StartPosition::Standard,
+            side,
+            opponent,
+            time_control,
+            discards_game,
+            focused_row: START_ROW,
+        }
+    }
+
+    /// Changes the option on `row` to the next (or previous) choice.
+    pub fn cycle(&mut self, row: usize, forward: bool) {
+        match row {
+            0 => self.start = match (self.start, forward) {
+                (StartPosition::Standard, true) | (StartPosition::SavedPosition, false) => StartPosition::CurrentPosition,
+                (StartPosition::CurrentPosition, true) | (StartPosition::Standard, false) => StartPosition::SavedPosition,
+                (StartPosition::SavedPosition, true) | (StartPosition::CurrentPosition, false) => StartPosition::Standard,
+            },
+            1 => self.side = match self.side {
+                board::Color::White => board::Color::Black,
+                board::Color::Black => board::Color::White,
+            },
+            2 => self.opponent = match self.opponent {
+                Opponent::Human => Opponent::Computer,
+                Opponent::Computer => Opponent::Human,
+            },
+            3 => {
+                let count = TIME_CONTROLS.len();
+                self.time_control = if forward {
+                    (self.time_control + 1) % count
+                }
+                else {
+                    (self.time_control + count - 1) % count
+                };
+            },
+            _ => {}
+        }
+    }
+
+    /// Returns the text shown on `row`.
+    pub fn row_label(&self, row: usize) -> String {
+        match row {
+            0 => format!("Start: {}", match self.start {
+                StartPosition::Standard => "initial position",
+                StartPosition::CurrentPosition => "current position",
+                StartPosition::SavedPosition => POSITION_FILE,
+            }),
+            1 => format!("You play: {}", match self.side {
+                board::Color::White => "White",
+                board::Color::Black => "Black",
+            }),
+            2 => format!("Opponent: {}", match self.opponent {
+                Opponent::Human => "human",
+                Opponent::Computer => "computer",
+            }),
+            3 => format!("Time: {}", match TIME_CONTROLS[self.time_control] {
+                Some(control) => control.name,
+                None => "untimed",
+            }),
+            START_ROW if self.discards_game => String::from("Abandon game and start"),
+            START_ROW => String::from("Start"),
+            CANCEL_ROW => String::from("Cancel"),
+            _ => String::new(),
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use std::fmt;
+
+use alvinw_chess::{game::Game, game::GameState, pos::BoardPos, piece::PieceType};
+
+use crate::rules::{legal_moves, play, Move};
+
+/// Returns the algebraic name of a square, e.g. `e4`.
+pub fn square_name(pos: &BoardPos) -> String {
+    let file = (b'a' + pos.file()) as char;
+    let rank = (b'1' + pos.rank()) as char;
+    format!("{}{}", file, rank)
+}
+
+/// Returns the SAN letter of a piece, empty for pawns.
+pub fn piece_letter(piece: PieceType) -> &'static str {
+    match piece {
+        PieceType::Pawn => "",
+        PieceType::Knight => "N",
+        PieceType::Bishop => "B",
+        PieceType::Rook => "R",
+        PieceType::Queen => "Q",
+        PieceType::King => "K",
+    }
+}
+
+/// Builds the Standard Algebraic Notation of a move.
+///
+/// `before` is the position the move was played in and `after` the position
+/// once the move (and promotion, if any) has been applied.
+pub fn to_san(before: &Game, after: &Game, from: &BoardPos, to: &BoardPos, promotion: Option<PieceType>) -> String {
+    let mut san = String::new();
+
+    let tile = match before.get_tile(from) {
+        Some(tile) => tile,
+        None => return san,
+    };
+    let piece = tile.piece();
+    let file_distance = to.file() as i32 - from.file() as i32;
+
+    if piece == PieceType::King && file_distance.abs() == 2 {
+        san.push_str(if file_distance > 0 { "O-O" } else { "O-O-O" });
+    }
+    else {
+        let is_capture = before.get_tile(to).is_some() ||
+            (piece == PieceType::Pawn && file_distance != 0);
+
+        if piece == PieceType::Pawn {
+            if is_capture {
+                san.push((b'a' + from.file()) as char);
+            }
+        }
+        else {
+            san.push_str(piece_letter(piece));
+            san.push_str(&disambiguation(before, from, to, piece));
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&square_name(to));
+
+        if let Some(promotion) = promotion {
+            san.push('=');
+            san.push_str(piece_letter(promotion));
+        }
+    }
+
+    match after.get_state() {
+        GameState::Check(_) => san.push('+'),
+        GameState::Checkmate(_) => san.push('#'),
+        _ => {}
+    }
+    san
+}
+
+/// Finds the file and/or rank needed to tell `from` apart from other pieces
+/// of the same kind that can also reach `to`.
+fn disambiguation(game: &Game, from: &BoardPos, to: &BoardPos, piece: PieceType) -> String {
+    let mut same_file = false;
+    let mut same_rank = false;
+    let mut ambiguous = false;
+
+    for rank in 0..8 {
+        for file in 0..8 {
+            let other = BoardPos::new(file, rank);
+            if other == *from {
+                continue;
+            }
+            let is_same_piece = match game.get_tile(&other) {
+                Some(tile) => tile.piece() == piece && tile.color() == game.current_turn(),
+                None => false,
+            };
+            if !is_same_piece {
+                continue;
+            }
+            if let Ok(moves) = game.get_legal_moves(&other) {
+                if moves.into_iter().any(|mv| mv == *to) {
+                    ambiguous = true;
+                    same_file |= other.file() == from.file();
+                    same_rank |= other.rank() == from.rank();
+                }
+            }
+        }
+    }
+
+    let name = square_name(from);
+    if !ambiguous {
+        String::new()
+    }
+    else if !same_file {
+        name[0..1].to_string()
+    }
+    else if !same_rank {
+        name[1..2].to_string()
+    }
+    else {
+        name
+    }
+}
+
+/// Why a SAN move could not be resolved against a position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SanError {
+    /// The text is not a SAN move at all.
+    Invalid,
+    /// No legal move matches the text.
+    Illegal,
+    /// More than one legal move matches the text.
+    Ambiguous,
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Invalid => write!(f, "not a valid move"),
+            SanError::Illegal => write!(f, "illegal move"),
+            SanError::Ambiguous => write!(f, "ambiguous move"),
+        }
+    }
+}
+
+/// Resolves a SAN move such as `Nbd7`, `exd6`, `O-O` or `e8=Q+` against the
+/// legal moves of the position.
+pub fn parse_san(game: &Game, text: &str) -> Result<Move, SanError> {
+    let cleaned: String = text
+        .trim_end_matches(['+', '#', '!', '?'])
+        .chars()
+        .filter(|c| *c != 'x' && *c != '=')
+        .map(|c| if c == '0' { 'O' } else { c })
+        .collect();
+
+    let candidates = legal_moves(game);
+    let matches: Vec<Move> = if cleaned == "O-O" || cleaned == "O-O-O" {
+        let direction = if cleaned == "O-O" { 2 } else { -2 };
+        candidates.into_iter().filter(|mv| {
+            is_piece(game, &mv.from, PieceType::King) &&
+                mv.to.file() as i32 - mv.from.file() as i32 == direction
+        }).collect()
+    }
+    else {
+        let mut chars: Vec<char> = cleaned.chars().collect();
+
+        let piece = match chars.first().and_then(|c| letter_piece(*c)) {
+            Some(piece) => {
+                chars.remove(0);
+                piece
+            },
+            None => PieceType::Pawn,
+        };
+        let promotion = match chars.last().and_then(|c| letter_piece(*c)) {
+            Some(promotion) if piece == PieceType::Pawn => {
+                chars.pop();
+                Some(promotion)
+            },
+            _ => None,
+        };
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(SanError::Invalid);
+        }
+        let to = parse_square(chars[chars.len() - 2], chars[chars.len() - 1]).ok_or(SanError::Invalid)?;
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' => from_file = Some(*c as u8 - b'a'),
+                '1'..='8' => from_rank = Some(*c as u8 - b'1'),
+                _ => return Err(SanError::Invalid),
+            }
+        }
+
+        candidates.into_iter().filter(|mv| {
+            mv.to == to &&
+                mv.promotion == promotion &&
+                is_piece(game, &mv.from, piece) &&
+                from_file.is_none_or(|file| mv.from.file() == file) &&
+                from_rank.is_none_or(|rank| mv.from.rank() == rank)
+        }).collect()
+    };
+
+    match matches.len() {
+        0 => Err(SanError::Illegal),
+        1 => Ok(matches[0].clone()),
+        _ => Err(SanError::Ambiguous),
+    }
+}
+
+/// Parses a square such as `e4` from its file and rank characters.
+pub fn parse_square(file: char, rank: char) -> Option<BoardPos> {
+    if !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+    Some(BoardPos::new(file as u8 - b'a', rank as u8 - b'1'))
+}
+
+fn letter_piece(letter: char) -> Option<PieceType> {
+    match letter {
+        'N' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'R' => Some(PieceType::Rook),
+        'Q' => Some(PieceType::Queen),
+        'K' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+fn is_piece(game: &Game, pos: &BoardPos, piece: PieceType) -> bool {
+    match game.get_tile(pos) {
+        Some(tile) => tile.piece() == piece,
+        None => false,
+    }
+}
+
+/// Writes a move in UCI long algebraic notation, e.g. `e2e4` or `e7e8q`.
+pub fn to_uci(from: &BoardPos, to: &BoardPos, promotion: Option<PieceType>) -> String {
+    let mut uci = square_name(from) + &square_name(to);
+    if let Some(piece) = promotion {
+        uci.push_str(&piece_letter(piece).to_lowercase());
+    }
+    uci
+}
+
+/// Reads a move in UCI long algebraic notation. The move is not checked for legality.
+pub fn parse_uci(text: &str) -> Option<Move> {
+    let chars: Vec<char> = text.trim().chars().collect();
+    if chars.len() != 4 && chars.len() != 5 {
+        return None;
+    }
+
+    let from = parse_square(chars[0], chars[1])?;
+    let to = parse_square(chars[2], chars[3])?;
+    let promotion = match chars.get(4) {
+        Some(c) => Some(letter_piece(c.to_ascii_uppercase()).filter(|piece| *piece != PieceType::King)?),
+        None => None,
+    };
+    Some(Move { from, to, promotion })
+}
+
+/// Reads a move typed in UCI or SAN notation.
+///
+/// UCI is tried first: a UCI move such as `g1f3` also reads as a pawn move
+/// with file and rank given in SAN, which never matches a legal move.
+pub fn parse_move(game: &Game, text: &str) -> Result<Move, SanError> {
+    if let Some(mv) = parse_uci(text) {
+        if legal_moves(game).contains(&mv) {
+            return Ok(mv);
+        }
+    }
+    parse_san(game, text)
+}
+
+/// Lists every legal move with its SAN and UCI names, in that order.
+pub fn move_names(game: &Game) -> Vec<(String, String, Move)> {
+    legal_moves(game).into_iter().filter_map(|mv| {
+        let after = play(game, &mv)?;
+        let san = to_san(game, &after, &mv.from, &mv.to, mv.promotion);
+        let uci = to_uci(&mv.from, &mv.to, mv.promotion);
+        Some((san, uci, mv))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(moves: &[&str]) -> Game {
+        moves.iter().fold(Game::new(), |game, san| {
+            let mv = parse_san(&game, san).unwrap();
+            play(&game, &mv).unwrap()
+        })
+    }
+
+    /// Writes the UCI move `uci` in SAN.
+    fn san(game: &Game, uci: &str) -> String {
+        let mv = parse_uci(uci).unwrap();
+        let after = play(game, &mv).unwrap();
+        to_san(game, &after, &mv.from, &mv.to, mv.promotion)
+    }
+
+    #[test]
+    fn disambiguates_by_file() {
+        let game = line(&["Nf3", "a6", "d3", "a5"]);
+        assert_eq!(san(&game, "b1d2"), "Nbd2");
+        assert_eq!(san(&game, "f3d2"), "Nfd2");
+    }
+
+    #[test]
+    fn disambiguates_by_rank() {
+        let game = line(&["d3", "a6", "Nf3", "a5", "Nd4", "h6", "Nd2", "h5"]);
+        assert_eq!(san(&game, "d2f3"), "N2f3");
+        assert_eq!(san(&game, "d4f3"), "N4f3");
+    }
+
+    #[test]
+    fn disambiguates_by_file_and_rank() {
+        // Knights on h8, h4 and f4 all reach g6.
+        let game = line(&["h4", "g5", "hxg5", "h6", "gxh6", "Bg7", "hxg7", "Nf6", "gxh8=N", "a6",
+                          "Nh3", "a5", "Nf4", "a4", "d3", "b6", "Nd2", "b5", "Nf3", "c6", "Nh4", "c5"]);
+        assert_eq!(san(&game, "h4g6"), "Nh4g6");
+    }
+
+    #[test]
+    fn en_passant_is_a_capture() {
+        let game = line(&["e4", "a6", "e5", "d5"]);
+        assert_eq!(san(&game, "e5d6"), "exd6");
+    }
+
+    #[test]
+    fn castling_with_check() {
+        let game = line(&["d4", "e5", "dxe5", "d6", "exd6", "Bxd6", "Nf3", "Nc6", "Nc3", "Be6",
+                          "e3", "Qe7", "Kd2", "Bb4", "a3"]);
+        assert_eq!(san(&game, "e8c8"), "O-O-O+");
+    }
+
+    #[test]
+    fn promotion_with_mate() {
+        let game = line(&["h4", "g5", "hxg5", "Nh6", "gxh6", "Bg7", "hxg7", "a6"]);
+        assert_eq!(san(&game, "g7h8q"), "gxh8=Q#");
+        assert_eq!(san(&game, "g7h8n"), "gxh8=N");
+    }
+
+    #[test]
+    fn reads_typed_moves_in_either_notation() {
+        let uci = |game: &Game, text: &str| parse_move(game, text).map(|mv| to_uci(&mv.from, &mv.to, mv.promotion));
+
+        let game = Game::new();
+        assert_eq!(uci(&game, "Nf3"), Ok(String::from("g1f3")));
+        assert_eq!(uci(&game, "g1f3"), Ok(String::from("g1f3")));
+        assert_eq!(uci(&game, "g1g3"), Err(SanError::Illegal));
+        assert_eq!(uci(&game, "Ng3"), Err(SanError::Illegal));
+
+        let game = line(&["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5"]);
+        assert_eq!(uci(&game, "e1g1"), Ok(String::from("e1g1")));
+        assert_eq!(uci(&game, "O-O"), Ok(String::from("e1g1")));
+
+        let game = line(&["e4", "f5", "exf5", "Kf7", "f6", "Kg6", "fxe7", "a6"]);
+        assert_eq!(uci(&game, "e7e8q"), Ok(String::from("e7e8q")));
+        assert_eq!(uci(&game, "e7e8n"), Ok(String::from("e7e8n")));
+        assert_eq!(uci(&game, "e8=Q"), Ok(String::from("e7e8q")));
+        assert_eq!(uci(&game, "e7e8"), Err(SanError::Illegal));
+    }
+
+    #[test]
+    fn san_parses_back_to_the_same_move() {
+        let game = line(&["d3", "a6", "Nf3", "a5", "Nd4", "h6", "Nd2", "h5"]);
+        for (san, uci, _) in move_names(&game) {
+            let mv = parse_san(&game, &san).unwrap();
+            assert_eq!(to_uci(&mv.from, &mv.to, mv.promotion), uci);
+        }
+    }
+}
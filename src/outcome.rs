@@ -0,0 +1,206 @@
+use std::fmt;
+
+use alvinw_chess::{game::Game, game::GameState, piece::PieceType, board};
+
+use crate::fen::Fen;
+use crate::history::GameHistory;
+use crate::rules::{has_mating_material, legal_moves, opponent};
+
+/// Why a game ended.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    Resignation,
+    Agreement,
+    Timeout,
+    /// A flag fell but the opponent had no mating material left.
+    TimeoutVsInsufficientMaterial,
+}
+
+/// The result of a finished game.
+#[derive(Clone, Copy, PartialEq)]
+pub struct GameResult {
+    /// The winning side, `None` for a draw.
+    pub winner: Option<board::Color>,
+    pub termination: Termination,
+}
+
+impl GameResult {
+    /// Creates a result where `winner` won.
+    pub fn win(winner: board::Color, termination: Termination) -> GameResult {
+        GameResult { winner: Some(winner), termination }
+    }
+
+    /// Creates a drawn result.
+    pub fn draw(termination: Termination) -> GameResult {
+        GameResult { winner: None, termination }
+    }
+
+    /// Returns the PGN result token, e.g. `1-0`.
+    pub fn pgn_token(&self) -> &'static str {
+        match self.winner {
+            Some(board::Color::White) => "1-0",
+            Some(board::Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+
+    /// Returns a headline such as "White wins".
+    pub fn headline(&self) -> &'static str {
+        match self.winner {
+            Some(board::Color::White) => "White wins",
+            Some(board::Color::Black) => "Black wins",
+            None => "Draw",
+        }
+    }
+
+    /// Returns the reason the game ended, e.g. "by checkmate".
+    pub fn reason(&self) -> &'static str {
+        match self.termination {
+            Termination::Checkmate => "by checkmate",
+            Termination::Stalemate => "by stalemate",
+            Termination::ThreefoldRepetition => "by threefold repetition",
+            Termination::FiftyMoveRule => "by the fifty-move rule",
+            Termination::InsufficientMaterial => "by insufficient material",
+            Termination::Resignation => "by resignation",
+            Termination::Agreement => "by agreement",
+            Termination::Timeout => "on time",
+            Termination::TimeoutVsInsufficientMaterial => "by timeout vs insufficient material",
+        }
+    }
+}
+
+impl fmt::Debug for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} ({:?})", self.headline(), self.reason(), self.termination)
+    }
+}
+
+/// Checks the current position of the game for an outcome decided on the board.
+///
+/// The chess library only reports checkmate, so stalemate, repetition, the
+/// fifty-move rule and dead positions are detected here.
+pub fn detect(game: &Game, history: &GameHistory) -> Option<GameResult> {
+    if let GameState::Checkmate(_) = game.get_state() {
+        return Some(GameResult::win(opponent(game.current_turn()), Termination::Checkmate));
+    }
+    if legal_moves(game).is_empty() {
+        return Some(GameResult::draw(Termination::Stalemate));
+    }
+    if !has_mating_material(game, board::Color::White) && !has_mating_material(game, board::Color::Black) {
+        return Some(GameResult::draw(Termination::InsufficientMaterial));
+    }
+
+    let halfmove_clock = Fen::from_game(game, history).halfmove_clock as usize;
+    if halfmove_clock >= 100 {
+        return Some(GameResult::draw(Termination::FiftyMoveRule));
+    }
+
+    // Positions before the last capture or pawn move can never repeat.
+    let current = history.current();
+    let key = position_key(history, current);
+    let repetitions = (current - halfmove_clock.min(current)..=current)
+        .filter(|ply| position_key(history, *ply) == key)
+        .count();
+    if repetitions >= 3 {
+        return Some(GameResult::draw(Termination::ThreefoldRepetition));
+    }
+    None
+}
+
+/// Returns the result of `flagged` running out of time.
+pub fn timeout(game: &Game, flagged: board::Color) -> GameResult {
+    let winner = opponent(flagged);
+    if has_mating_material(game, winner) {
+        GameResult::win(winner, Termination::Timeout)
+    }
+    else {
+        GameResult::draw(Termination::TimeoutVsInsufficientMaterial)
+    }
+}
+
+/// Identifies the position after `ply` moves for repetitions: the placement,
+/// side to move, castling rights and the en passant square if the capture
+/// can actually be played.
+fn position_key(history: &GameHistory, ply: usize) -> String {
+    let game = history.position(ply);
+    let mut fen = Fen::at_ply(history, ply);
+    if let Some(square) = fen.en_passant.clone() {
+        let can_capture = legal_moves(game).iter().any(|mv| {
+            mv.to == square && game.get_tile(&mv.from).is_some_and(|tile| tile.piece() == PieceType::Pawn)
+        });
+        if !can_capture {
+            fen.en_passant = None;
+        }
+    }
+    fen.halfmove_clock = 0;
+    fen.fullmove_number = 1;
+    fen.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::MoveRecord;
+    use crate::notation::{parse_san, to_san};
+    use crate::rules::play;
+
+    /// Plays `moves` and returns the outcome detected after each of them.
+    fn outcomes(history: &mut GameHistory, moves: &[&str]) -> Vec<Option<GameResult>> {
+        moves.iter().map(|text| {
+            let game = history.current_position();
+            let mv = parse_san(&game, text).unwrap();
+            let next = play(&game, &mv).unwrap();
+            let san = to_san(&game, &next, &mv.from, &mv.to, mv.promotion);
+            history.push(MoveRecord { from: mv.from, to: mv.to, promotion: mv.promotion, san, captured: None, clock: None }, next.clone());
+            detect(&next, history)
+        }).collect()
+    }
+
+    fn new_history() -> GameHistory {
+        GameHistory::new(Game::new())
+    }
+
+    #[test]
+    fn checkmate() {
+        let results = outcomes(&mut new_history(), &["f3", "e5", "g4", "Qh4"]);
+        assert_eq!(results, [None, None, None, Some(GameResult::win(board::Color::Black, Termination::Checkmate))]);
+    }
+
+    #[test]
+    fn stalemate() {
+        let moves = ["e3", "a5", "Qh5", "Ra6", "Qxa5", "h5", "h4", "Rah6", "Qxc7", "f6",
+                     "Qxd7+", "Kf7", "Qxb7", "Qd3", "Qxb8", "Qh7", "Qxc8", "Kg6", "Qe6"];
+        let results = outcomes(&mut new_history(), &moves);
+        assert!(results[..moves.len() - 1].iter().all(Option::is_none));
+        assert_eq!(results[moves.len() - 1], Some(GameResult::draw(Termination::Stalemate)));
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let results = outcomes(&mut new_history(), &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert!(results[..7].iter().all(Option::is_none));
+        assert_eq!(results[7], Some(GameResult::draw(Termination::ThreefoldRepetition)));
+    }
+
+    #[test]
+    fn lost_castling_rights_change_the_position() {
+        // The position after 1. e4 e5 comes back twice, but without castling rights.
+        let moves = ["e4", "e5", "Ke2", "Ke7", "Ke1", "Ke8", "Ke2", "Ke7", "Ke1", "Ke8", "Ke2", "Ke7"];
+        let results = outcomes(&mut new_history(), &moves);
+        assert!(results[..11].iter().all(Option::is_none));
+        assert_eq!(results[11], Some(GameResult::draw(Termination::ThreefoldRepetition)));
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let fen = Fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 98 50").unwrap();
+        let mut history = GameHistory::from_fen(fen.to_game().unwrap(), fen);
+        let results = outcomes(&mut history, &["Nf3", "Nf6"]);
+        assert_eq!(results, [None, Some(GameResult::draw(Termination::FiftyMoveRule))]);
+    }
+}
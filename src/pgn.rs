@@ -0,0 +1,276 @@
+use std::fmt;
+use std::fs;
+use std::io;
+
+use alvinw_chess::game::Game;
+
+use crate::history::{GameHistory, MoveRecord};
+use crate::notation::{parse_san, to_san, SanError};
+use crate::rules::{captured_piece, play};
+use crate::utils::current_date;
+
+/// Error produced while importing a PGN file.
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    /// A move in the mainline could not be played.
+    Move {
+        /// Full-move number, as written in the move text.
+        move_number: usize,
+        san: String,
+        reason: SanError,
+    },
+    /// The game starts from a position given by a `FEN` or `SetUp` tag,
+    /// which cannot be set up on the board.
+    CustomStart,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::Io(err) => write!(f, "{}", err),
+            PgnError::Move { move_number, san, reason } =>
+                write!(f, "move {} ({}): {}", move_number, san, reason),
+            PgnError::CustomStart => write!(f, "games starting from a FEN position are not supported"),
+        }
+    }
+}
+
+impl From<io::Error> for PgnError {
+    fn from(err: io::Error) -> PgnError {
+        PgnError::Io(err)
+    }
+}
+
+/// Builds a PGN document with the Seven Tag Roster for the whole mainline,
+/// including moves that were taken back but can still be redone.
+pub fn export_pgn(history: &GameHistory, result: &str) -> String {
+    let (year, month, day) = current_date();
+    let mut pgn = String::new();
+
+    pgn.push_str("[Event \"Casual game\"]\n");
+    pgn.push_str("[Site \"?\"]\n");
+    pgn.push_str(&format!("[Date \"{:04}.{:02}.{:02}\"]\n", year, month, day));
+    pgn.push_str("[Round \"-\"]\n");
+    pgn.push_str("[White \"White\"]\n");
+    pgn.push_str("[Black \"Black\"]\n");
+    pgn.push_str(&format!("[Result \"{}\"]\n\n", result));
+
+    let mut tokens = Vec::new();
+    for (i, record) in history.moves().iter().enumerate() {
+        if i % 2 == 0 {
+            tokens.push(format!("{}.", i / 2 + 1));
+        }
+        tokens.push(record.san.clone());
+    }
+    tokens.push(result.to_string());
+
+    // PGN export format keeps lines below 80 characters.
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + 1 + token.len() > 79 {
+            pgn.push('\n');
+            line_len = 0;
+        }
+        else if line_len > 0 {
+            pgn.push(' ');
+            line_len += 1;
+        }
+        line_len += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+    pgn
+}
+
+/// Games saved from the board are named after this prefix and the time they
+/// were saved in milliseconds.
+pub const SAVED_GAME_PREFIX: &str = "game_";
+
+/// Returns the most recently saved game in the working directory.
+pub fn last_saved_game() -> Option<String> {
+    fs::read_dir(".").ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|name| {
+            let saved_at: u64 = name.strip_prefix(SAVED_GAME_PREFIX)?.strip_suffix(".pgn")?.parse().ok()?;
+            Some((saved_at, name))
+        })
+        .max()
+        .map(|(_, name)| name)
+}
+
+/// Writes the game to a PGN file.
+pub fn save_pgn(path: &str, history: &GameHistory, result: &str) -> io::Result<()> {
+    fs::write(path, export_pgn(history, result))
+}
+
+/// Reads a PGN file and plays its mainline from `start`.
+pub fn load_pgn(path: &str, start: Game) -> Result<GameHistory, PgnError> {
+    let text = fs::read_to_string(path)?;
+    import_pgn(&text, start)
+}
+
+/// Plays the mainline of the first game in a PGN document.
+///
+/// Tag pairs, comments, variations and annotation glyphs are skipped. Games
+/// with a `FEN` or `SetUp` tag are rejected rather than played from `start`.
+pub fn import_pgn(text: &str, start: Game) -> Result<GameHistory, PgnError> {
+    if tag_names(text).iter().any(|name| name == "FEN" || name == "SetUp") {
+        return Err(PgnError::CustomStart);
+    }
+
+    let mut history = GameHistory::new(start.clone());
+    let mut game = start;
+
+    for (ply, token) in mainline_tokens(text).into_iter().enumerate() {
+        if token == "1-0" || token == "0-1" || token == "1/2-1/2" || token == "*" {
+            break;
+        }
+
+        let move_number = ply / 2 + 1;
+        let mv = parse_san(&game, &token).map_err(|reason| PgnError::Move {
+            move_number,
+            san: token.clone(),
+            reason,
+        })?;
+        let next = play(&game, &mv).ok_or(PgnError::Move {
+            move_number,
+            san: token.clone(),
+            reason: SanError::Illegal,
+        })?;
+
+        let san = to_san(&game, &next, &mv.from, &mv.to, mv.promotion);
+        let captured = captured_piece(&game, &next);
+        history.push(MoveRecord { from: mv.from, to: mv.to, promotion: mv.promotion, san, captured, clock: None }, next.clone());
+        game = next;
+    }
+    Ok(history)
+}
+
+/// Returns the names of the tag pairs at the start of lines.
+fn tag_names(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| line.trim_start().strip_prefix('['))
+        .filter_map(|tag| tag.split_whitespace().next())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Splits the move text into SAN tokens, dropping everything that is not a move.
+fn mainline_tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut variation_depth = 0;
+    let mut in_comment = false;
+    let mut in_line_comment = false;
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        if in_line_comment {
+            in_line_comment = c != '\n';
+            continue;
+        }
+        if in_comment {
+            in_comment = c != '}';
+            continue;
+        }
+        if in_tag {
+            in_tag = c != ']';
+            continue;
+        }
+
+        match c {
+            '{' => in_comment = true,
+            ';' => in_line_comment = true,
+            '[' => in_tag = true,
+            '(' => variation_depth += 1,
+            ')' => variation_depth -= 1,
+            _ if variation_depth > 0 => {},
+            c if c.is_whitespace() || c == '.' => {
+                push_token(&mut tokens, &mut current);
+                continue;
+            },
+            c => {
+                current.push(c);
+                continue;
+            },
+        }
+        push_token(&mut tokens, &mut current);
+    }
+    push_token(&mut tokens, &mut current);
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<String>, current: &mut String) {
+    let token = std::mem::take(current);
+    let is_move_number = token.chars().all(|c| c.is_ascii_digit());
+    if !token.is_empty() && !is_move_number && !token.starts_with('$') {
+        tokens.push(token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sans(history: &GameHistory) -> Vec<&str> {
+        history.moves().iter().map(|record| record.san.as_str()).collect()
+    }
+
+    #[test]
+    fn exported_games_import_again() {
+        let text = "1. d4 e5 2. dxe5 d6 3. exd6 Bxd6 4. Nf3 Nc6 5. Nc3 Be6 6. e3 Qe7 7. Kd2 Bb4 \
+                    8. a3 O-O-O+ 9. Ke2 Bxc3 10. bxc3 Bc4+ 11. Ke1 Qxe3+ 12. Be2 Bxe2 13. Qxe2 Qxc3+ \
+                    14. Bd2 Qxa1+ 0-1";
+        let history = import_pgn(text, Game::new()).unwrap();
+        assert_eq!(history.moves().len(), 28);
+
+        let exported = export_pgn(&history, "0-1");
+        assert!(exported.contains("[Result \"0-1\"]"));
+        assert!(exported.lines().all(|line| line.len() < 80));
+        assert!(exported.trim_end().ends_with("Qxa1+ 0-1"));
+
+        let imported = import_pgn(&exported, Game::new()).unwrap();
+        assert_eq!(sans(&imported), sans(&history));
+    }
+
+    #[test]
+    fn export_includes_moves_after_the_current_one() {
+        let mut history = import_pgn("1. e4 e5 2. Nf3 Nc6", Game::new()).unwrap();
+        history.go_to(1);
+        let exported = export_pgn(&history, "1-0");
+        assert!(exported.trim_end().ends_with("1. e4 e5 2. Nf3 Nc6 1-0"));
+    }
+
+    #[test]
+    fn skips_comments_variations_and_glyphs() {
+        let text = "[Event \"Casual game\"]\n[Result \"1-0\"]\n\n\
+                    1. e4 {best by test} e5 (1... c5 2. Nf3 (2. c3) d6) 2. Nf3 $1 Nc6 ; the main line\n\
+                    3. Bb5! a6?! 1-0";
+        let history = import_pgn(text, Game::new()).unwrap();
+        assert_eq!(sans(&history), ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+    }
+
+    #[test]
+    fn reports_illegal_moves_with_their_number() {
+        let err = import_pgn("1. e4 e5 2. Ke3 Nc6", Game::new()).err().unwrap();
+        assert!(matches!(err, PgnError::Move { move_number: 2, reason: SanError::Illegal, .. }));
+        assert_eq!(err.to_string(), "move 2 (Ke3): illegal move");
+
+        let err = import_pgn("1. e4 e5 2. Nf3 Qh4 3. Nxh4 Qxf2", Game::new()).err().unwrap();
+        assert!(matches!(err, PgnError::Move { move_number: 3, reason: SanError::Illegal, .. }));
+    }
+
+    #[test]
+    fn reports_ambiguous_moves_with_their_number() {
+        let err = import_pgn("1. Nf3 a6 2. d3 a5 3. Nd2", Game::new()).err().unwrap();
+        assert!(matches!(err, PgnError::Move { move_number: 3, reason: SanError::Ambiguous, .. }));
+        assert_eq!(err.to_string(), "move 3 (Nd2): ambiguous move");
+    }
+
+    #[test]
+    fn rejects_custom_start_positions() {
+        let text = "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. O-O *";
+        assert!(matches!(import_pgn(text, Game::new()), Err(PgnError::CustomStart)));
+    }
+}
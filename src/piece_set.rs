@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::Path;
+
+use alvinw_chess::{piece::PieceType, board};
+use graphics::ImageSize;
+use opengl_graphics::{Texture, TextureSettings};
+
+/// Piece order of a sprite sheet row, also used for naming piece images.
+const PIECE_ORDER: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Rook,
+    PieceType::Pawn,
+];
+
+/// How a single piece is drawn.
+pub enum PieceImage<'a, T> {
+    /// A character of the chess font.
+    Glyph(char),
+    /// A part of a texture, given as `[x, y, width, height]` in pixels.
+    Sprite(&'a T, [f64; 4]),
+}
+
+/// A set of piece artwork.
+pub struct PieceSet<T> {
+    pub name: String,
+    textures: Vec<T>,
+    /// Texture index and source rectangle of each piece, White first, in
+    /// `PIECE_ORDER`. Empty for the font.
+    sprites: Vec<(usize, [f64; 4])>,
+}
+
+impl<T: ImageSize> PieceSet<T> {
+    /// Creates the piece set drawn with the chess font.
+    pub fn font() -> PieceSet<T> {
+        PieceSet {
+            name: String::from("font"),
+            textures: Vec::new(),
+            sprites: Vec::new(),
+        }
+    }
+
+    /// Creates a piece set from a sprite sheet with White's pieces on the top
+    /// row and Black's below, each row in the order king, queen, bishop,
+    /// knight, rook, pawn.
+    pub fn from_sprite_sheet(name: &str, texture: T) -> PieceSet<T> {
+        let (width, height) = texture.get_size();
+        let sprite_width = width as f64 / 6.0;
+        let sprite_height = height as f64 / 2.0;
+
+        let mut sprites = Vec::with_capacity(12);
+        for row in 0..2 {
+            for column in 0..6 {
+                sprites.push((0, [column as f64 * sprite_width, row as f64 * sprite_height, sprite_width, sprite_height]));
+            }
+        }
+        PieceSet { name: name.to_string(), textures: vec![texture], sprites }
+    }
+
+    /// Creates a piece set from one texture per piece, White first, in the
+    /// same order as a sprite sheet.
+    pub fn from_images(name: &str, textures: Vec<T>) -> PieceSet<T> {
+        let sprites = textures.iter().enumerate().map(|(index, texture)| {
+            let (width, height) = texture.get_size();
+            (index, [0.0, 0.0, width as f64, height as f64])
+        }).collect();
+        PieceSet { name: name.to_string(), textures, sprites }
+    }
+
+    /// Returns how to draw a piece.
+    pub fn image(&self, piece: PieceType, color: board::Color) -> PieceImage<T> {
+        if self.sprites.is_empty() {
+            return PieceImage::Glyph(piece_glyph(piece, color));
+        }
+
+        let column = PIECE_ORDER.iter().position(|other| *other == piece).unwrap_or(0);
+        let row = if color == board::Color::White { 0 } else { 1 };
+        let (texture, rect) = self.sprites[row * 6 + column];
+        PieceImage::Sprite(&self.textures[texture], rect)
+    }
+}
+
+impl PieceSet<Texture> {
+    /// Loads a piece set from a directory holding either `sprites.png` or
+    /// one image per piece named like `wK.png` and `bN.png`.
+    pub fn load(dir: &Path) -> Result<PieceSet<Texture>, String> {
+        let name = dir.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        let settings = TextureSettings::new();
+
+        let sheet = dir.join("sprites.png");
+        if sheet.is_file() {
+            return Ok(PieceSet::from_sprite_sheet(&name, Texture::from_path(&sheet, &settings)?));
+        }
+
+        let mut textures = Vec::with_capacity(12);
+        for color in ['w', 'b'] {
+            for piece in PIECE_ORDER {
+                let letter = match piece {
+                    PieceType::King => 'K',
+                    PieceType::Queen => 'Q',
+                    PieceType::Bishop => 'B',
+                    PieceType::Knight => 'N',
+                    PieceType::Rook => 'R',
+                    PieceType::Pawn => 'P',
+                };
+                let path = dir.join(format!("{}{}.png", color, letter));
+                textures.push(Texture::from_path(&path, &settings)?);
+            }
+        }
+        Ok(PieceSet::from_images(&name, textures))
+    }
+}
+
+/// Returns the font piece set followed by every piece set found in the
+/// subdirectories of `dir`. Sets that fail to load are skipped.
+pub fn load_piece_sets(dir: &str) -> Vec<PieceSet<Texture>> {
+    let mut piece_sets = vec![PieceSet::font()];
+
+    let mut dirs: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.is_dir()).collect(),
+        Err(_) => return piece_sets,
+    };
+    dirs.sort();
+
+    for path in dirs {
+        match PieceSet::load(&path) {
+            Ok(piece_set) => piece_sets.push(piece_set),
+            Err(err) => println!("Could not load piece set {}: {}", path.display(), err),
+        }
+    }
+    piece_sets
+}
+
+/// Maps a piece to its character in the CHEQ chess font.
+fn piece_glyph(piece: PieceType, color: board::Color) -> char {
+    match piece {
+        PieceType::Pawn => match color {
+            board::Color::White => 'p',
+            board::Color::Black => 'o',
+        },
+        PieceType::Knight => match color {
+            board::Color::White => 'h',
+            board::Color::Black => 'j',
+        },
+        PieceType::Bishop => match color {
+            board::Color::White => 'b',
+            board::Color::Black => 'n',
+        },
+        PieceType::Rook => match color {
+            board::Color::White => 'r',
+            board::Color::Black => 't',
+        },
+        PieceType::Queen => match color {
+            board::Color::White => 'q',
+            board::Color::Black => 'w',
+        },
+        PieceType::King => match color {
+            board::Color::White => 'k',
+            board::Color::Black => 'l',
+        },
+    }
+}
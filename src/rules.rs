@@ -0,0 +1,183 @@
+use alvinw_chess::{game::Game, game::GameState, pos::BoardPos, piece::PieceType, board};
+
+/// A move from one square to another, with the promotion piece if it promotes.
+#[derive(Clone, PartialEq)]
+pub struct Move {
+    pub from: BoardPos,
+    pub to: BoardPos,
+    pub promotion: Option<PieceType>,
+}
+
+/// The pieces a pawn may promote to, in the order shown in the promotion menu.
+pub const PROMOTION_PIECES: [PieceType; 4] = [
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+];
+
+/// Lists every legal move for the side to move, expanding promotions.
+pub fn legal_moves(game: &Game) -> Vec<Move> {
+    let mut moves = Vec::new();
+
+    for rank in 0..8 {
+        for file in 0..8 {
+            let from = BoardPos::new(file, rank);
+            let is_pawn = match game.get_tile(&from) {
+                Some(tile) if tile.color() == game.current_turn() => tile.piece() == PieceType::Pawn,
+                _ => continue,
+            };
+            let targets = match game.get_legal_moves(&from) {
+                Ok(targets) => targets,
+                Err(_) => continue,
+            };
+
+            for to in targets {
+                if is_pawn && (to.rank() == 0 || to.rank() == 7) {
+                    for piece in PROMOTION_PIECES {
+                        moves.push(Move { from: from.clone(), to: to.clone(), promotion: Some(piece) });
+                    }
+                }
+                else {
+                    moves.push(Move { from: from.clone(), to, promotion: None });
+                }
+            }
+        }
+    }
+    moves
+}
+
+/// Plays a move on a copy of the game, returning `None` if it is not legal.
+pub fn play(game: &Game, mv: &Move) -> Option<Game> {
+    let mut next = game.clone();
+    if next.move_piece(&mv.from, &mv.to).is_err() {
+        return None;
+    }
+
+    match (next.get_state(), mv.promotion) {
+        (GameState::PromotionRequired(_), Some(piece)) => {
+            next.promote(piece);
+        },
+        (GameState::PromotionRequired(_), None) => return None,
+        (_, Some(_)) => return None,
+        _ => {}
+    }
+    Some(next)
+}
+
+/// Finds the piece captured by the move from `before` to `after`: a piece of
+/// the side not to move in `before` that is gone from its square in `after`.
+/// This also catches en passant, where the captured pawn is not on the target
+/// square, and reports promoted pieces as what they became.
+pub fn captured_piece(before: &Game, after: &Game) -> Option<PieceType> {
+    let mover = before.current_turn();
+    for rank in 0..8 {
+        for file in 0..8 {
+            let pos = BoardPos::new(file, rank);
+            let victim = match before.get_tile(&pos) {
+                Some(tile) if tile.color() != mover => tile.piece(),
+                _ => continue,
+            };
+            let still_there = match after.get_tile(&pos) {
+                Some(tile) => tile.color() != mover,
+                None => false,
+            };
+            if !still_there {
+                return Some(victim);
+            }
+        }
+    }
+    None
+}
+
+/// Returns whether `color` could still deliver checkmate with the help of
+/// the opponent's moves.
+///
+/// A lone king, a king and knight against a lone king, and kings with
+/// bishops that all stand on squares of one colour can never mate. The
+/// position is dead when neither side can.
+pub fn has_mating_material(game: &Game, color: board::Color) -> bool {
+    let mut pieces = Vec::new();
+    for rank in 0..8 {
+        for file in 0..8 {
+            if let Some(tile) = game.get_tile(&BoardPos::new(file, rank)) {
+                if tile.piece() != PieceType::King {
+                    pieces.push((tile.piece(), tile.color(), (file + rank) % 2));
+                }
+            }
+        }
+    }
+    can_checkmate(&pieces, color)
+}
+
+/// Decides `has_mating_material` from the pieces other than the kings, given
+/// as piece, colour and square colour.
+fn can_checkmate(pieces: &[(PieceType, board::Color, u8)], color: board::Color) -> bool {
+    let own: Vec<_> = pieces.iter().filter(|(_, owner, _)| *owner == color).collect();
+    let other: Vec<_> = pieces.iter().filter(|(_, owner, _)| *owner != color).collect();
+
+    if own.iter().any(|(piece, _, _)| matches!(piece, PieceType::Pawn | PieceType::Rook | PieceType::Queen)) {
+        return true;
+    }
+    if own.iter().any(|(piece, _, _)| *piece == PieceType::Knight) {
+        // A knight needs another piece of either side to box the king in.
+        return own.len() >= 2 || !other.is_empty();
+    }
+    match own.first() {
+        None => false,
+        Some((_, _, square_color)) => {
+            // Bishops on one colour only mate when a piece that can stand on
+            // the other colour blocks the king.
+            own.iter().any(|(_, _, other_color)| other_color != square_color) ||
+                other.iter().any(|(piece, _, other_color)| *piece != PieceType::Bishop || other_color != square_color)
+        }
+    }
+}
+
+/// Returns the colour that is not `color`.
+pub fn opponent(color: board::Color) -> board::Color {
+    match color {
+        board::Color::White => board::Color::Black,
+        board::Color::Black => board::Color::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alvinw_chess::board::Color::{Black, White};
+    use alvinw_chess::piece::PieceType::{Bishop, Knight, Pawn, Rook};
+
+    fn dead(pieces: &[(PieceType, board::Color, u8)]) -> bool {
+        !can_checkmate(pieces, White) && !can_checkmate(pieces, Black)
+    }
+
+    #[test]
+    fn dead_positions() {
+        assert!(dead(&[]));
+        assert!(dead(&[(Knight, White, 0)]));
+        assert!(dead(&[(Bishop, Black, 1)]));
+        assert!(dead(&[(Bishop, White, 0), (Bishop, Black, 0)]));
+        assert!(dead(&[(Bishop, White, 1), (Bishop, White, 1), (Bishop, Black, 1)]));
+    }
+
+    #[test]
+    fn mate_is_still_possible() {
+        assert!(!dead(&[(Bishop, White, 0), (Bishop, Black, 1)]));
+        assert!(!dead(&[(Knight, White, 0), (Knight, Black, 1)]));
+        assert!(!dead(&[(Bishop, White, 0), (Knight, Black, 0)]));
+        assert!(!dead(&[(Knight, White, 0), (Knight, White, 1)]));
+        assert!(!dead(&[(Bishop, White, 0), (Bishop, White, 1)]));
+        assert!(!dead(&[(Pawn, Black, 0)]));
+    }
+
+    #[test]
+    fn lone_minor_piece_needs_help_to_win_on_time() {
+        assert!(!can_checkmate(&[(Knight, White, 0)], White));
+        assert!(can_checkmate(&[(Knight, White, 0), (Pawn, Black, 1)], White));
+        assert!(!can_checkmate(&[(Bishop, White, 0), (Bishop, Black, 0)], White));
+        assert!(can_checkmate(&[(Bishop, White, 0), (Rook, Black, 0)], White));
+        assert!(!can_checkmate(&[(Rook, Black, 0)], White));
+    }
+}
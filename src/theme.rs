@@ -0,0 +1,194 @@
+use graphics::types::Color;
+
+/// Colours of the board and the panels around it.
+#[derive(Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Window background.
+    pub background: Color,
+    pub light_square: Color,
+    pub dark_square: Color,
+    pub border: Color,
+    /// Colour of pieces drawn with the chess font.
+    pub pieces: Color,
+    pub last_move_from: Color,
+    pub last_move_to: Color,
+    pub annotation: Color,
+    pub keyboard_cursor: Color,
+    pub coordinates: Color,
+    /// Background of the move list, move input and active clock.
+    pub panel: Color,
+    pub panel_text: Color,
+    /// Buttons and the current move in the move list.
+    pub highlight: Color,
+}
+
+/// Names of the theme colours as written in the settings file.
+pub const COLOR_KEYS: [&str; 13] = [
+    "background",
+    "light_square",
+    "dark_square",
+    "border",
+    "pieces",
+    "last_move_from",
+    "last_move_to",
+    "annotation",
+    "keyboard_cursor",
+    "coordinates",
+    "panel",
+    "panel_text",
+    "highlight",
+];
+
+impl Theme {
+    /// Returns the colour stored under a key of `COLOR_KEYS`.
+    pub fn color(&self, key: &str) -> Option<Color> {
+        Some(match key {
+            "background" => self.background,
+            "light_square" => self.light_square,
+            "dark_square" => self.dark_square,
+            "border" => self.border,
+            "pieces" => self.pieces,
+            "last_move_from" => self.last_move_from,
+            "last_move_to" => self.last_move_to,
+            "annotation" => self.annotation,
+            "keyboard_cursor" => self.keyboard_cursor,
+            "coordinates" => self.coordinates,
+            "panel" => self.panel,
+            "panel_text" => self.panel_text,
+            "highlight" => self.highlight,
+            _ => return None,
+        })
+    }
+
+    /// Changes the colour stored under a key of `COLOR_KEYS`. Returns false
+    /// for unknown keys.
+    pub fn set_color(&mut self, key: &str, color: Color) -> bool {
+        let field = match key {
+            "background" => &mut self.background,
+            "light_square" => &mut self.light_square,
+            "dark_square" => &mut self.dark_square,
+            "border" => &mut self.border,
+            "pieces" => &mut self.pieces,
+            "last_move_from" => &mut self.last_move_from,
+            "last_move_to" => &mut self.last_move_to,
+            "annotation" => &mut self.annotation,
+            "keyboard_cursor" => &mut self.keyboard_cursor,
+            "coordinates" => &mut self.coordinates,
+            "panel" => &mut self.panel,
+            "panel_text" => &mut self.panel_text,
+            "highlight" => &mut self.highlight,
+            _ => return false,
+        };
+        *field = color;
+        true
+    }
+}
+
+/// Returns the themes that ship with the GUI, the original colours first.
+pub fn builtin_themes() -> Vec<Theme> {
+    vec![
+        Theme {
+            name: String::from("original"),
+            background: [0.3, 0.3, 0.5, 1.0],
+            light_square: [0.6, 0.45, 0.45, 1.0],
+            dark_square: [0.4, 0.3, 0.3, 1.0],
+            border: [0.0, 0.0, 0.0, 1.0],
+            pieces: [0.0, 0.0, 0.1, 1.0],
+            last_move_from: [0.55, 0.55, 0.35, 1.0],
+            last_move_to: [0.65, 0.65, 0.35, 1.0],
+            annotation: [0.1, 0.6, 0.2, 0.7],
+            keyboard_cursor: [0.95, 0.8, 0.2, 1.0],
+            coordinates: [0.85, 0.85, 0.85, 1.0],
+            panel: [0.2, 0.2, 0.35, 1.0],
+            panel_text: [0.9, 0.9, 0.9, 1.0],
+            highlight: [0.45, 0.45, 0.65, 1.0],
+        },
+        Theme {
+            name: String::from("classic-green"),
+            background: [0.19, 0.18, 0.17, 1.0],
+            light_square: [0.93, 0.93, 0.82, 1.0],
+            dark_square: [0.46, 0.59, 0.34, 1.0],
+            border: [0.1, 0.1, 0.1, 1.0],
+            pieces: [0.05, 0.05, 0.05, 1.0],
+            last_move_from: [0.8, 0.8, 0.45, 1.0],
+            last_move_to: [0.73, 0.79, 0.3, 1.0],
+            annotation: [0.9, 0.55, 0.1, 0.8],
+            keyboard_cursor: [0.2, 0.5, 0.9, 1.0],
+            coordinates: [0.85, 0.85, 0.85, 1.0],
+            panel: [0.25, 0.24, 0.22, 1.0],
+            panel_text: [0.9, 0.9, 0.9, 1.0],
+            highlight: [0.4, 0.55, 0.3, 1.0],
+        },
+        Theme {
+            name: String::from("wood"),
+            background: [0.22, 0.16, 0.11, 1.0],
+            light_square: [0.94, 0.85, 0.71, 1.0],
+            dark_square: [0.71, 0.53, 0.39, 1.0],
+            border: [0.25, 0.15, 0.08, 1.0],
+            pieces: [0.1, 0.05, 0.0, 1.0],
+            last_move_from: [0.8, 0.78, 0.45, 1.0],
+            last_move_to: [0.67, 0.64, 0.23, 1.0],
+            annotation: [0.1, 0.5, 0.2, 0.75],
+            keyboard_cursor: [0.1, 0.4, 0.8, 1.0],
+            coordinates: [0.95, 0.9, 0.8, 1.0],
+            panel: [0.32, 0.24, 0.17, 1.0],
+            panel_text: [0.95, 0.9, 0.8, 1.0],
+            highlight: [0.55, 0.4, 0.25, 1.0],
+        },
+        Theme {
+            name: String::from("high-contrast"),
+            background: [0.0, 0.0, 0.0, 1.0],
+            light_square: [1.0, 1.0, 1.0, 1.0],
+            dark_square: [0.55, 0.55, 0.55, 1.0],
+            border: [1.0, 1.0, 0.0, 1.0],
+            pieces: [0.0, 0.0, 0.0, 1.0],
+            last_move_from: [1.0, 0.85, 0.3, 1.0],
+            last_move_to: [1.0, 0.75, 0.0, 1.0],
+            annotation: [0.0, 0.4, 1.0, 0.9],
+            keyboard_cursor: [1.0, 0.0, 1.0, 1.0],
+            coordinates: [1.0, 1.0, 1.0, 1.0],
+            panel: [0.1, 0.1, 0.1, 1.0],
+            panel_text: [1.0, 1.0, 1.0, 1.0],
+            highlight: [0.0, 0.3, 0.8, 1.0],
+        },
+        // Blue and orange from the Okabe-Ito palette, which stay distinct
+        // under the common forms of colour blindness.
+        Theme {
+            name: String::from("colour-blind"),
+            background: [0.15, 0.17, 0.22, 1.0],
+            light_square: [0.87, 0.89, 0.93, 1.0],
+            dark_square: [0.45, 0.55, 0.7, 1.0],
+            border: [0.05, 0.05, 0.05, 1.0],
+            pieces: [0.02, 0.02, 0.05, 1.0],
+            last_move_from: [0.94, 0.89, 0.26, 1.0],
+            last_move_to: [0.9, 0.62, 0.0, 1.0],
+            annotation: [0.84, 0.37, 0.0, 0.8],
+            keyboard_cursor: [0.8, 0.47, 0.65, 1.0],
+            coordinates: [0.9, 0.9, 0.9, 1.0],
+            panel: [0.22, 0.25, 0.32, 1.0],
+            panel_text: [0.95, 0.95, 0.95, 1.0],
+            highlight: [0.0, 0.45, 0.7, 1.0],
+        },
+    ]
+}
+
+/// Parses a colour written as `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(text: &str) -> Option<Color> {
+    let hex = text.strip_prefix('#')?;
+    if (hex.len() != 6 && hex.len() != 8) || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut color = [1.0; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()? as f32 / 255.0;
+    }
+    Some(color)
+}
+
+/// Writes a colour as `#rrggbbaa`.
+pub fn format_color(color: Color) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}{:02x}", channel(color[0]), channel(color[1]), channel(color[2]), channel(color[3]))
+}
@@ -0,0 +1,288 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::fen::START_FEN;
+
+/// How long to wait for `uciok` and `readyok` before giving up on an engine.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Evaluation reported by an engine, from the side to move's point of view.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Score {
+    /// Centipawns.
+    Centipawns(i32),
+    /// Moves until mate, negative when the side to move is getting mated.
+    Mate(i32),
+}
+
+/// The latest `info` line of a search.
+#[derive(Clone, PartialEq)]
+pub struct UciInfo {
+    pub depth: u32,
+    pub score: Option<Score>,
+    /// Principal variation in UCI long algebraic notation.
+    pub pv: Vec<String>,
+}
+
+impl UciInfo {
+    /// Parses the fields of an `info` line that the GUI shows. Returns `None`
+    /// for lines without a score or principal variation, like `info string`.
+    pub fn parse(line: &str) -> Option<UciInfo> {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("info") {
+            return None;
+        }
+
+        let mut info = UciInfo { depth: 0, score: None, pv: Vec::new() };
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => info.depth = tokens.next()?.parse().ok()?,
+                "score" => {
+                    let kind = tokens.next()?;
+                    let value = tokens.next()?.parse().ok()?;
+                    info.score = match kind {
+                        "cp" => Some(Score::Centipawns(value)),
+                        "mate" => Some(Score::Mate(value)),
+                        _ => None,
+                    };
+                },
+                "pv" => info.pv = tokens.by_ref().map(String::from).collect(),
+                "string" => return None,
+                _ => {}
+            }
+        }
+
+        if info.score.is_none() && info.pv.is_empty() {
+            return None;
+        }
+        Some(info)
+    }
+}
+
+/// An external engine speaking the Universal Chess Interface over stdin/stdout.
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    /// The engine's name from `id name`, or its path.
+    pub name: String,
+    /// The latest search information.
+    pub info: Option<UciInfo>,
+    searching: bool,
+    /// Number of `bestmove` replies still expected from stopped searches.
+    stale_results: usize,
+}
+
+impl UciEngine {
+    /// Starts the engine binary and waits for it to finish the `uci`/`isready` handshake.
+    pub fn spawn(path: &str) -> io::Result<UciEngine> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        let (sender, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UciEngine {
+            child,
+            stdin,
+            lines,
+            name: path.to_string(),
+            info: None,
+            searching: false,
+            stale_results: 0,
+        };
+        engine.send("uci")?;
+        engine.wait_for("uciok")?;
+        engine.send("ucinewgame")?;
+        engine.send("isready")?;
+        engine.wait_for("readyok")?;
+        Ok(engine)
+    }
+
+    /// Returns whether a search is running.
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// Starts searching the position reached from `start_fen` by `moves`,
+    /// given in UCI notation. Without a move time the search runs until
+    /// `stop` is called.
+    pub fn go(&mut self, start_fen: &str, moves: &[String], movetime: Option<u64>) -> io::Result<()> {
+        self.stop()?;
+        self.info = None;
+
+        let mut position = if start_fen == START_FEN {
+            String::from("position startpos")
+        }
+        else {
+            format!("position fen {}", start_fen)
+        };
+        if !moves.is_empty() {
+            position.push_str(&format!(" moves {}", moves.join(" ")));
+        }
+        self.send(&position)?;
+        match movetime {
+            Some(ms) => self.send(&format!("go movetime {}", ms))?,
+            None => self.send("go infinite")?,
+        }
+        self.searching = true;
+        Ok(())
+    }
+
+    /// Stops the running search and discards its result.
+    pub fn stop(&mut self) -> io::Result<()> {
+        if self.searching {
+            self.send("stop")?;
+            self.searching = false;
+            self.stale_results += 1;
+        }
+        Ok(())
+    }
+
+    /// Reads everything the engine has printed so far and returns the best
+    /// move once the current search has finished. Fails once the engine has
+    /// exited.
+    pub fn poll(&mut self) -> io::Result<Option<String>> {
+        loop {
+            let line = match self.lines.try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Disconnected) =>
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "engine exited")),
+            };
+
+            if let Some(info) = UciInfo::parse(&line) {
+                if self.searching {
+                    self.info = Some(info);
+                }
+            }
+            else if let Some(rest) = line.strip_prefix("bestmove") {
+                if self.stale_results > 0 {
+                    self.stale_results -= 1;
+                    continue;
+                }
+                self.searching = false;
+                return Ok(rest.split_whitespace().next().map(String::from));
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    fn wait_for(&mut self, expected: &str) -> io::Result<()> {
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(timeout) {
+                Ok(line) => {
+                    if let Some(name) = line.strip_prefix("id name ") {
+                        self.name = name.trim().to_string();
+                    }
+                    if line.trim() == expected {
+                        return Ok(());
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) =>
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, format!("engine did not send {}", expected))),
+                Err(RecvTimeoutError::Disconnected) =>
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "engine exited")),
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        self.send("quit").ok();
+        thread::sleep(Duration::from_millis(50));
+        if let Ok(None) = self.child.try_wait() {
+            self.child.kill().ok();
+        }
+        self.child.wait().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOCK_ENGINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/mock_engine.sh");
+
+    /// Polls until the engine answers, failing the test after a few seconds.
+    fn wait_for_best_move(engine: &mut UciEngine) -> io::Result<Option<String>> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Some(best) = engine.poll()? {
+                return Ok(Some(best));
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        Ok(None)
+    }
+
+    #[test]
+    fn parses_info_lines() {
+        let info = UciInfo::parse("info depth 12 seldepth 18 score cp -35 nodes 1000 pv e7e5 g1f3").unwrap();
+        assert_eq!(info.depth, 12);
+        assert!(info.score == Some(Score::Centipawns(-35)));
+        assert_eq!(info.pv, vec!["e7e5", "g1f3"]);
+
+        let info = UciInfo::parse("info depth 5 score mate 2").unwrap();
+        assert!(info.score == Some(Score::Mate(2)));
+        assert!(UciInfo::parse("info string hello").is_none());
+        assert!(UciInfo::parse("info depth 3 nodes 10").is_none());
+    }
+
+    #[test]
+    fn handshake_reads_engine_name() {
+        let engine = UciEngine::spawn(MOCK_ENGINE).unwrap();
+        assert_eq!(engine.name, "Mock Engine");
+        assert!(!engine.is_searching());
+    }
+
+    #[test]
+    fn search_reports_info_and_best_move() {
+        let mut engine = UciEngine::spawn(MOCK_ENGINE).unwrap();
+        engine.go(START_FEN, &[], Some(100)).unwrap();
+        assert!(engine.is_searching());
+
+        assert_eq!(wait_for_best_move(&mut engine).unwrap().as_deref(), Some("e2e4"));
+        assert!(!engine.is_searching());
+        let info = engine.info.clone().unwrap();
+        assert_eq!(info.depth, 2);
+        assert!(info.score == Some(Score::Mate(-3)));
+        assert_eq!(info.pv, vec!["e2e4", "g1f3"]);
+    }
+
+    #[test]
+    fn other_start_positions_are_sent_as_fen() {
+        let mut engine = UciEngine::spawn(MOCK_ENGINE).unwrap();
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+        engine.go(fen, &[String::from("e2e4")], Some(100)).unwrap();
+        assert_eq!(wait_for_best_move(&mut engine).unwrap().as_deref(), Some("e7e5"));
+    }
+
+    #[test]
+    fn exited_engine_is_an_error() {
+        let mut engine = UciEngine::spawn(MOCK_ENGINE).unwrap();
+        engine.send("quit").unwrap();
+        assert!(wait_for_best_move(&mut engine).is_err());
+    }
+}
@@ -1,6 +1,77 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::cell::Cell;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
+/// Returns the wall-clock time in milliseconds since the Unix epoch.
+///
+/// Only use this for timestamps and seeds, measure durations with a `Clock`.
 pub fn current_time() -> u64 {
-    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
     return since_epoch.as_secs() * 1000 + since_epoch.subsec_millis() as u64;
-}
\ No newline at end of file
+}
+
+/// A source of time for animations and game clocks.
+pub trait Clock {
+    /// Returns milliseconds elapsed since an arbitrary fixed point.
+    fn now(&self) -> u64;
+}
+
+/// A clock that never goes backwards, unaffected by changes to the system time.
+pub struct MonotonicClock {
+    start: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> MonotonicClock {
+        MonotonicClock { start: Instant::now() }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> MonotonicClock {
+        MonotonicClock::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+}
+
+/// A clock that only moves when advanced by hand, for deterministic tests.
+pub struct FakeClock {
+    now: Cell<u64>,
+}
+
+impl FakeClock {
+    pub fn new(start: u64) -> FakeClock {
+        FakeClock { now: Cell::new(start) }
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.now.set(self.now.get() + ms);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+}
+
+/// Returns today's UTC date as `(year, month, day)`.
+pub fn current_date() -> (i64, u32, u32) {
+    let days = (current_time() / 1000 / 86400) as i64;
+
+    // Civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
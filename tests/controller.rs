@@ -0,0 +1,161 @@
+use std::rc::Rc;
+
+use alvinw_chess::board::Color;
+use alvinw_chess::game::Game;
+use alvinw_chess::piece::PieceType;
+use alvinw_chess::pos::BoardPos;
+
+use olindba_chess_gui::gameboard_controller::{CommandError, CommandOutcome, GameAction};
+use olindba_chess_gui::notation::parse_square;
+use olindba_chess_gui::outcome::{GameResult, Termination};
+use olindba_chess_gui::rules::Move;
+use olindba_chess_gui::utils::FakeClock;
+use olindba_chess_gui::GameboardController;
+
+fn controller() -> (GameboardController, Rc<FakeClock>) {
+    let time = Rc::new(FakeClock::new(0));
+    let mut controller = GameboardController::with_clock(Game::new(), time.clone());
+    controller.save_finished_games = false;
+    (controller, time)
+}
+
+fn sq(name: &str) -> BoardPos {
+    let mut chars = name.chars();
+    parse_square(chars.next().unwrap(), chars.next().unwrap()).unwrap()
+}
+
+/// Plays a move once the previous move's animation has finished.
+fn play(controller: &mut GameboardController, time: &FakeClock, from: &str, to: &str) -> Result<CommandOutcome, CommandError> {
+    time.advance(1000);
+    controller.try_move(sq(from), sq(to))
+}
+
+fn moved(san: &str) -> Result<CommandOutcome, CommandError> {
+    Ok(CommandOutcome::Moved { san: String::from(san), result: None })
+}
+
+#[test]
+fn selecting_then_clicking_a_target_moves() {
+    let (mut controller, _) = controller();
+    assert_eq!(controller.select(sq("e4")), Ok(CommandOutcome::Deselected));
+    assert_eq!(controller.select(sq("e7")), Ok(CommandOutcome::Deselected));
+    assert_eq!(controller.select(sq("g1")), Ok(CommandOutcome::Selected));
+    assert_eq!(controller.select(sq("e2")), Ok(CommandOutcome::Selected));
+    assert_eq!(controller.select(sq("e4")), moved("e4"));
+    assert_eq!(controller.history.moves().len(), 1);
+}
+
+#[test]
+fn illegal_moves_leave_the_board_alone() {
+    let (mut controller, time) = controller();
+    assert_eq!(play(&mut controller, &time, "e2", "e5"), Err(CommandError::IllegalMove));
+    assert_eq!(play(&mut controller, &time, "e4", "e5"), Err(CommandError::IllegalMove));
+    assert_eq!(play(&mut controller, &time, "e7", "e5"), Err(CommandError::IllegalMove));
+    assert_eq!(play(&mut controller, &time, "g1", "g3"), Err(CommandError::IllegalMove));
+    assert!(controller.history.moves().is_empty());
+    assert!(controller.gameboard.current_turn() == Color::White);
+    assert!(controller.gameboard.get_tile(&sq("e2")).is_some());
+}
+
+#[test]
+fn moves_wait_for_the_animation() {
+    let (mut controller, time) = controller();
+    assert_eq!(play(&mut controller, &time, "e2", "e4"), moved("e4"));
+    assert_eq!(controller.try_move(sq("e7"), sq("e5")), Err(CommandError::Animating));
+    assert_eq!(play(&mut controller, &time, "e7", "e5"), moved("e5"));
+}
+
+#[test]
+fn engine_side_cannot_be_moved_by_hand() {
+    let (mut controller, time) = controller();
+    controller.engine_color = Some(Color::Black);
+    assert_eq!(play(&mut controller, &time, "e2", "e4"), moved("e4"));
+    assert_eq!(play(&mut controller, &time, "e7", "e5"), Err(CommandError::EngineTurn));
+}
+
+#[test]
+fn undo_against_the_computer_returns_to_the_human() {
+    let (mut controller, time) = controller();
+    controller.engine_color = Some(Color::Black);
+    assert_eq!(play(&mut controller, &time, "e2", "e4"), moved("e4"));
+    assert!(controller.play_move(&Move { from: sq("e7"), to: sq("e5"), promotion: None }));
+
+    controller.undo();
+    assert_eq!(controller.history.current(), 0);
+    assert!(controller.gameboard.current_turn() == Color::White);
+
+    controller.redo();
+    assert_eq!(controller.history.current(), 2);
+    assert!(controller.gameboard.current_turn() == Color::White);
+
+    // While the computer is still thinking only the human's move is taken back.
+    controller.undo();
+    assert_eq!(play(&mut controller, &time, "d2", "d4"), moved("d4"));
+    controller.undo();
+    assert_eq!(controller.history.current(), 0);
+}
+
+#[test]
+fn full_game_ends_in_checkmate() {
+    let (mut controller, time) = controller();
+    assert_eq!(play(&mut controller, &time, "f2", "f3"), moved("f3"));
+    assert_eq!(play(&mut controller, &time, "e7", "e5"), moved("e5"));
+    assert_eq!(play(&mut controller, &time, "g2", "g4"), moved("g4"));
+    assert_eq!(play(&mut controller, &time, "d8", "h4"), Ok(CommandOutcome::Moved {
+        san: String::from("Qh4#"),
+        result: Some(GameResult::win(Color::Black, Termination::Checkmate)),
+    }));
+
+    assert_eq!(play(&mut controller, &time, "e2", "e4"), Err(CommandError::GameOver));
+    assert_eq!(controller.select(sq("e2")), Err(CommandError::GameOver));
+    assert_eq!(controller.history.moves().len(), 4);
+
+    controller.reset();
+    assert!(controller.result.is_none());
+    assert!(controller.history.moves().is_empty());
+    assert_eq!(play(&mut controller, &time, "e2", "e4"), moved("e4"));
+}
+
+#[test]
+fn promotion_waits_for_a_piece() {
+    let (mut controller, time) = controller();
+    for (from, to) in [("h2", "h4"), ("g7", "g5"), ("h4", "g5"), ("h7", "h6"), ("g5", "h6"),
+                       ("f8", "g7"), ("h6", "g7"), ("g8", "f6")] {
+        assert!(matches!(play(&mut controller, &time, from, to), Ok(CommandOutcome::Moved { .. })));
+    }
+
+    assert_eq!(play(&mut controller, &time, "g7", "h8"), Ok(CommandOutcome::PromotionPending));
+    assert_eq!(play(&mut controller, &time, "a7", "a6"), Err(CommandError::PromotionPending));
+    assert_eq!(controller.select(sq("a7")), Err(CommandError::PromotionPending));
+
+    assert_eq!(controller.choose_promotion(PieceType::King), Err(CommandError::InvalidPromotion));
+    assert_eq!(controller.choose_promotion(PieceType::Pawn), Err(CommandError::InvalidPromotion));
+    assert!(controller.ongoing_promotion.is_some());
+
+    assert_eq!(controller.choose_promotion(PieceType::Queen), moved("gxh8=Q+"));
+    assert_eq!(controller.choose_promotion(PieceType::Queen), Err(CommandError::NoPromotionPending));
+    assert!(controller.gameboard.get_tile(&sq("h8"))
+        .is_some_and(|tile| tile.piece() == PieceType::Queen && tile.color() == Color::White));
+    assert_eq!(controller.history.moves().len(), 9);
+    assert!(controller.gameboard.current_turn() == Color::Black);
+}
+
+#[test]
+fn undo_takes_back_the_end_of_the_game() {
+    let (mut controller, time) = controller();
+    for (from, to) in [("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")] {
+        assert!(matches!(play(&mut controller, &time, from, to), Ok(CommandOutcome::Moved { .. })));
+    }
+    assert!(controller.result.is_some());
+
+    controller.undo();
+    assert!(controller.result.is_none());
+    assert_eq!(play(&mut controller, &time, "d8", "g5"), moved("Qg5"));
+
+    controller.perform_action(GameAction::Resign);
+    assert_eq!(play(&mut controller, &time, "e2", "e4"), Err(CommandError::GameOver));
+    controller.undo();
+    assert!(controller.result.is_none());
+    assert_eq!(controller.history.moves().len(), 4);
+    assert!(controller.gameboard.current_turn() == Color::Black);
+}
@@ -0,0 +1,31 @@
+#!/bin/sh
+# A minimal UCI engine for the tests. It answers every search with e2e4, or
+# with e7e5 when the position was given as FEN.
+position="position startpos"
+while read -r line; do
+    case "$line" in
+        uci)
+            echo "id name Mock Engine"
+            echo "uciok"
+            ;;
+        isready)
+            echo "readyok"
+            ;;
+        position*)
+            position="$line"
+            ;;
+        go*)
+            case "$position" in
+                "position fen"*) best=e7e5 ;;
+                *) best=e2e4 ;;
+            esac
+            echo "info string searching"
+            echo "info depth 1 score cp 20 pv $best"
+            echo "info depth 2 score mate -3 nodes 812 pv $best g1f3"
+            echo "bestmove $best ponder g1f3"
+            ;;
+        quit)
+            exit 0
+            ;;
+    esac
+done